@@ -0,0 +1,67 @@
+//! A lazy static initialized by an async function, awaited on the in-repo single-threaded executor.
+//! Every iteration runs one `block_on`, so compare `async_lazy_local` with `async_lazy_static_local`,
+//! which does the same with lazy_static!, to isolate the cost of awaiting an initialized AsyncLazy.
+
+use std::hint::black_box;
+
+use rust_benchmarks::executor::{block_on, yield_now, LocalExecutor};
+use rust_benchmarks::harness::Bencher;
+use rust_benchmarks::lazy::AsyncLazy;
+
+use super::{COMPILED_REGEX, LONG_REGEX, TEST_EMAIL};
+
+/// Stands in for reading the pattern from a file or a socket
+async fn load_regex() -> regex::Regex {
+    yield_now().await;
+    regex::Regex::new(LONG_REGEX).unwrap()
+}
+
+static COMPILED_REGEX_ASYNC: AsyncLazy<regex::Regex> = AsyncLazy::new(|| Box::pin(load_regex()));
+
+/// The regex is compiled by AsyncLazy and awaited inside block_on
+pub(crate) fn async_lazy_local(b: &mut Bencher) {
+    b.iter(|| {
+        let is_match = block_on(async { COMPILED_REGEX_ASYNC.get().await.is_match(TEST_EMAIL) });
+        black_box(is_match);
+    });
+}
+
+pub(crate) fn async_lazy_local_test() {
+    let is_match = block_on(async { COMPILED_REGEX_ASYNC.get().await.is_match(TEST_EMAIL) });
+    assert!(is_match);
+}
+
+/// The regex is compiled within lazy_static and used inside block_on, the baseline for async_lazy_local
+pub(crate) fn async_lazy_static_local(b: &mut Bencher) {
+    b.iter(|| {
+        let is_match = block_on(async { COMPILED_REGEX.is_match(TEST_EMAIL) });
+        black_box(is_match);
+    });
+}
+
+pub(crate) fn async_lazy_static_local_test() {
+    let is_match = block_on(async { COMPILED_REGEX.is_match(TEST_EMAIL) });
+    assert!(is_match);
+}
+
+/// Tasks on the same executor race for an uninitialized AsyncLazy, only one of them runs the initializer
+pub(crate) fn async_lazy_tasks_test() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static INIT_COUNT: AtomicUsize = AtomicUsize::new(0);
+    static COMPILED_REGEX_TASKS: AsyncLazy<regex::Regex> = AsyncLazy::new(|| {
+        Box::pin(async {
+            INIT_COUNT.fetch_add(1, Ordering::SeqCst);
+            load_regex().await
+        })
+    });
+
+    let executor = LocalExecutor::new();
+    for _ in 0..8 {
+        executor.spawn(async {
+            assert!(COMPILED_REGEX_TASKS.get().await.is_match(TEST_EMAIL));
+        });
+    }
+    executor.run();
+    assert_eq!(INIT_COUNT.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,55 @@
+//! The first `is_match` call of each lazy strategy, which includes compiling `LONG_REGEX`.
+//! Every sample runs in a fresh process, so the static is always uninitialized.
+
+use rust_benchmarks::harness::Bencher;
+
+use super::{
+    compiled_regex_once_lock, COMPILED_REGEX, COMPILED_REGEX_HAND_ROLLED, COMPILED_REGEX_LAZY_LOCK,
+    COMPILED_REGEX_ONCE_CELL, COMPILED_REGEX_UNSYNC, LONG_REGEX, TEST_EMAIL,
+};
+
+/// The regex is compiled and used directly, the baseline with no lazy initialization at all
+pub(crate) fn cold_start_vanilla_rust_local(b: &mut Bencher) {
+    b.iter_cold_start(|| regex::Regex::new(LONG_REGEX).unwrap().is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled within lazy_static at the root module
+pub(crate) fn cold_start_lazy_static_local(b: &mut Bencher) {
+    b.iter_cold_start(|| COMPILED_REGEX.is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled within lazy_static in a sub-module
+pub(crate) fn cold_start_lazy_static_inner(b: &mut Bencher) {
+    b.iter_cold_start(|| super::inner::COMPILED_REGEX_INNER.is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled by once_cell::sync::Lazy
+pub(crate) fn cold_start_once_cell_lazy(b: &mut Bencher) {
+    b.iter_cold_start(|| COMPILED_REGEX_ONCE_CELL.is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled by the in-repo rust_benchmarks::lazy::Lazy
+pub(crate) fn cold_start_hand_rolled_lazy(b: &mut Bencher) {
+    b.iter_cold_start(|| COMPILED_REGEX_HAND_ROLLED.is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled by the no_std rust_benchmarks::spin::SpinLazy
+#[cfg(feature = "spin")]
+pub(crate) fn cold_start_spin_lazy(b: &mut Bencher) {
+    b.iter_cold_start(|| super::COMPILED_REGEX_SPIN.is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled by std::sync::LazyLock
+pub(crate) fn cold_start_std_lazy_lock(b: &mut Bencher) {
+    b.iter_cold_start(|| COMPILED_REGEX_LAZY_LOCK.is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled by std::sync::OnceLock::get_or_init
+pub(crate) fn cold_start_std_once_lock(b: &mut Bencher) {
+    b.iter_cold_start(|| compiled_regex_once_lock().is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled once per thread by once_cell::unsync::Lazy stored in thread_local!
+pub(crate) fn cold_start_once_cell_unsync_thread_local(b: &mut Bencher) {
+    b.iter_cold_start(|| COMPILED_REGEX_UNSYNC.with(|re| re.is_match(TEST_EMAIL)));
+}
@@ -0,0 +1,72 @@
+//! The same `is_match` call as in the single-threaded benches, made from 1..N threads at once
+//! to see how each lazy strategy scales under contention.
+
+use rust_benchmarks::harness::Bencher;
+
+use super::reload::{COMPILED_REGEX_EPOCH, COMPILED_REGEX_RELOADABLE};
+use super::{
+    compiled_regex_clone_cell, compiled_regex_once_lock, COMPILED_REGEX, COMPILED_REGEX_CLONE,
+    COMPILED_REGEX_HAND_ROLLED, COMPILED_REGEX_LAZY_LOCK, COMPILED_REGEX_ONCE_CELL,
+    COMPILED_REGEX_UNSYNC, TEST_EMAIL,
+};
+
+/// The regex is compiled within lazy_static at the root module
+pub(crate) fn contention_lazy_static_local(b: &mut Bencher) {
+    b.iter_contended(|| COMPILED_REGEX.is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled within lazy_static in a sub-module
+pub(crate) fn contention_lazy_static_inner(b: &mut Bencher) {
+    b.iter_contended(|| super::inner::COMPILED_REGEX_INNER.is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled by once_cell::sync::Lazy
+pub(crate) fn contention_once_cell_lazy(b: &mut Bencher) {
+    b.iter_contended(|| COMPILED_REGEX_ONCE_CELL.is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled by the in-repo rust_benchmarks::lazy::Lazy
+pub(crate) fn contention_hand_rolled_lazy(b: &mut Bencher) {
+    b.iter_contended(|| COMPILED_REGEX_HAND_ROLLED.is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled by the no_std rust_benchmarks::spin::SpinLazy
+#[cfg(feature = "spin")]
+pub(crate) fn contention_spin_lazy(b: &mut Bencher) {
+    b.iter_contended(|| super::COMPILED_REGEX_SPIN.is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled by std::sync::LazyLock
+pub(crate) fn contention_std_lazy_lock(b: &mut Bencher) {
+    b.iter_contended(|| COMPILED_REGEX_LAZY_LOCK.is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled by std::sync::OnceLock::get_or_init
+pub(crate) fn contention_std_once_lock(b: &mut Bencher) {
+    b.iter_contended(|| compiled_regex_once_lock().is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled once per thread by once_cell::unsync::Lazy stored in thread_local!
+pub(crate) fn contention_once_cell_unsync_thread_local(b: &mut Bencher) {
+    b.iter_contended(|| COMPILED_REGEX_UNSYNC.with(|re| re.is_match(TEST_EMAIL)));
+}
+
+/// A clone of the regex compiled by lazy_static is stored in thread_local! of each thread
+pub(crate) fn contention_thread_local_clone(b: &mut Bencher) {
+    b.iter_contended(|| COMPILED_REGEX_CLONE.with(|re| re.is_match(TEST_EMAIL)));
+}
+
+/// A clone of the regex compiled by lazy_static is stored in a const-initialized thread-local OnceCell
+pub(crate) fn contention_thread_local_once_cell(b: &mut Bencher) {
+    b.iter_contended(|| compiled_regex_clone_cell(|re| re.is_match(TEST_EMAIL)));
+}
+
+/// The regex is compiled by ReloadableLazy and read under a RwLock read lock
+pub(crate) fn contention_reloadable_lazy(b: &mut Bencher) {
+    b.iter_contended(|| COMPILED_REGEX_RELOADABLE.get().is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled by EpochLazy and read through an atomic pointer guarded by an epoch counter
+pub(crate) fn contention_epoch_lazy(b: &mut Bencher) {
+    b.iter_contended(|| COMPILED_REGEX_EPOCH.get().is_match(TEST_EMAIL));
+}
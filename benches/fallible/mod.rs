@@ -0,0 +1,95 @@
+//! Fallible lazy statics that keep the `regex::Error` instead of calling `unwrap()`.
+//! Compare `try_lazy` and `retry_lazy` with `hand_rolled_lazy` to see the overhead of
+//! the `Result` check on the success path.
+
+use std::hint::black_box;
+
+use rust_benchmarks::harness::Bencher;
+use rust_benchmarks::lazy::{RetryLazy, TryLazy};
+
+use super::{LONG_REGEX, TEST_EMAIL};
+
+/// An unclosed group, fails to compile
+const INVALID_REGEX: &str = "(";
+
+static COMPILED_REGEX_TRY_LAZY: TryLazy<regex::Regex, regex::Error> =
+    TryLazy::new(|| regex::Regex::new(LONG_REGEX));
+
+static COMPILED_REGEX_RETRY_LAZY: RetryLazy<regex::Regex, regex::Error> =
+    RetryLazy::new(|| regex::Regex::new(LONG_REGEX));
+
+#[allow(clippy::invalid_regex)]
+static INVALID_REGEX_TRY_LAZY: TryLazy<regex::Regex, regex::Error> =
+    TryLazy::new(|| regex::Regex::new(INVALID_REGEX));
+
+#[allow(clippy::invalid_regex)]
+static INVALID_REGEX_RETRY_LAZY: RetryLazy<regex::Regex, regex::Error> =
+    RetryLazy::new(|| regex::Regex::new(INVALID_REGEX));
+
+/// The regex is compiled once by TryLazy, the Result is checked on every access
+pub(crate) fn try_lazy(b: &mut Bencher) {
+    b.iter(|| {
+        let is_match = COMPILED_REGEX_TRY_LAZY
+            .try_get()
+            .is_ok_and(|re| re.is_match(TEST_EMAIL));
+        black_box(is_match);
+    });
+}
+
+pub(crate) fn try_lazy_test() {
+    let is_match = COMPILED_REGEX_TRY_LAZY
+        .try_get()
+        .unwrap()
+        .is_match(TEST_EMAIL);
+    assert!(is_match);
+}
+
+/// The regex is compiled once by RetryLazy, same as try_lazy on the success path
+pub(crate) fn retry_lazy(b: &mut Bencher) {
+    b.iter(|| {
+        let is_match = COMPILED_REGEX_RETRY_LAZY
+            .try_get()
+            .is_ok_and(|re| re.is_match(TEST_EMAIL));
+        black_box(is_match);
+    });
+}
+
+pub(crate) fn retry_lazy_test() {
+    let is_match = COMPILED_REGEX_RETRY_LAZY
+        .try_get()
+        .unwrap()
+        .is_match(TEST_EMAIL);
+    assert!(is_match);
+}
+
+/// The invalid regex fails once and TryLazy returns the cached error on every access
+pub(crate) fn try_lazy_error(b: &mut Bencher) {
+    b.iter(|| {
+        let is_err = INVALID_REGEX_TRY_LAZY.try_get().is_err();
+        black_box(is_err);
+    });
+}
+
+pub(crate) fn try_lazy_error_test() {
+    assert!(matches!(
+        INVALID_REGEX_TRY_LAZY.try_get(),
+        Err(regex::Error::Syntax(_))
+    ));
+    assert!(INVALID_REGEX_TRY_LAZY.get().is_none());
+}
+
+/// The invalid regex is recompiled by RetryLazy on every access because the error is not cached
+pub(crate) fn retry_lazy_error(b: &mut Bencher) {
+    b.iter(|| {
+        let is_err = INVALID_REGEX_RETRY_LAZY.try_get().is_err();
+        black_box(is_err);
+    });
+}
+
+pub(crate) fn retry_lazy_error_test() {
+    assert!(matches!(
+        INVALID_REGEX_RETRY_LAZY.try_get(),
+        Err(regex::Error::Syntax(_))
+    ));
+    assert!(INVALID_REGEX_RETRY_LAZY.get().is_none());
+}
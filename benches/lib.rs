@@ -1,16 +1,23 @@
-#![feature(test)]
-
-extern crate test;
-use test::Bencher;
+use rust_benchmarks::harness::{self, BenchFn, Bencher, TestFn};
+use rust_benchmarks::lazy::Lazy;
+use std::cell::OnceCell;
+use std::hint::black_box;
+use std::sync::{LazyLock, OnceLock};
 
 #[macro_use]
 extern crate lazy_static;
 
+mod async_lazy;
+mod cold_start;
+mod contention;
 mod external_mod;
+mod fallible;
+mod matrix;
+mod poisoning;
+mod race;
+mod reload;
 
-/// Finds email addresses. Taken from https://github.com/rust-lang/regex/blob/master/tests/crazy.rs
-pub(crate) const LONG_REGEX: &str = r#"[a-z0-9!#$%&'*+/=?^_`{|}~-]+(?:\.[a-z0-9!#$%&'*+/=?^_`{|}~-]+)*@(?:[a-z0-9](?:[a-z0-9-]*[a-z0-9])?\.)+[a-z0-9](?:[a-z0-9-]*[a-z0-9])?"#;
-pub(crate) const TEST_EMAIL: &str = "max@example.com";
+pub(crate) use rust_benchmarks::workload::{LONG_REGEX, TEST_EMAIL};
 
 lazy_static! {
     pub(crate) static ref COMPILED_REGEX: regex::Regex = regex::Regex::new(LONG_REGEX).unwrap();
@@ -19,82 +26,53 @@ lazy_static! {
 static COMPILED_REGEX_ONCE_CELL: once_cell::sync::Lazy<regex::Regex> =
     once_cell::sync::Lazy::new(|| regex::Regex::new(LONG_REGEX).unwrap());
 
-/// The regex is compiled within lazy_static at the module level
-#[bench]
-fn lazy_static_local(b: &mut Bencher) {
-    b.iter(|| {
-        let is_match = COMPILED_REGEX.is_match(TEST_EMAIL);
-        test::black_box(is_match);
-    });
-}
+/// The in-repo Lazy from src/lazy/mod.rs
+pub(crate) static COMPILED_REGEX_HAND_ROLLED: Lazy<regex::Regex> =
+    Lazy::new(|| regex::Regex::new(LONG_REGEX).unwrap());
 
-#[test]
-fn lazy_static_local_test() {
-    let is_match = COMPILED_REGEX.is_match(TEST_EMAIL);
-    assert!(is_match);
-}
+/// The no_std SpinLazy from src/spin.rs
+#[cfg(feature = "spin")]
+pub(crate) static COMPILED_REGEX_SPIN: rust_benchmarks::spin::SpinLazy<regex::Regex> =
+    rust_benchmarks::spin::SpinLazy::new(|| regex::Regex::new(LONG_REGEX).unwrap());
 
-/// The regex is compiled once within the bench function
-#[bench]
-fn vanilla_rust_local(b: &mut Bencher) {
-    let compiled_regex = regex::Regex::new(LONG_REGEX).unwrap();
-    b.iter(|| {
-        let is_match = compiled_regex.is_match(TEST_EMAIL);
-        test::black_box(is_match);
-    });
-}
+pub(crate) static COMPILED_REGEX_LAZY_LOCK: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(LONG_REGEX).unwrap());
 
-#[test]
-fn vanilla_rust_local_test() {
-    let compiled_regex = regex::Regex::new(LONG_REGEX).unwrap();
-    let is_match = compiled_regex.is_match(TEST_EMAIL);
-    assert!(is_match);
-}
+static COMPILED_REGEX_ONCE_LOCK: OnceLock<regex::Regex> = OnceLock::new();
 
-/// The regex is compiled once by once_cell::sync::Lazy on the first use within the loop
-#[bench]
-fn once_cell_lazy(b: &mut Bencher) {
-    b.iter(|| {
-        let is_match = COMPILED_REGEX_ONCE_CELL.is_match(TEST_EMAIL);
-        test::black_box(is_match);
-    });
+thread_local! {
+    pub(crate) static COMPILED_REGEX_UNSYNC: once_cell::unsync::Lazy<regex::Regex> =
+        once_cell::unsync::Lazy::new(|| regex::Regex::new(LONG_REGEX).unwrap());
 }
 
-#[test]
-fn once_cell_lazy_test() {
-    let is_match = COMPILED_REGEX_ONCE_CELL.is_match(TEST_EMAIL);
-    assert!(is_match);
+thread_local! {
+    /// A per-thread clone of COMPILED_REGEX, so threads don't share the regex's internal cache pool
+    pub(crate) static COMPILED_REGEX_CLONE: regex::Regex = COMPILED_REGEX.clone();
+
+    /// Same as COMPILED_REGEX_CLONE, but the const initializer lets the compiler skip
+    /// the lazy initialization check of the thread local itself
+    pub(crate) static COMPILED_REGEX_CLONE_CELL: OnceCell<regex::Regex> = const { OnceCell::new() };
 }
 
-/// The regex is compiled within the bench function loop, which is obviously inefficient,
-/// but we do it to show the cost of its compilation
-#[bench]
-fn bad_rust_local(b: &mut Bencher) {
-    b.iter(|| {
-        let compiled_regex = regex::Regex::new(LONG_REGEX).unwrap();
-        let is_match = compiled_regex.is_match(TEST_EMAIL);
-        test::black_box(is_match);
-    });
+/// Clones COMPILED_REGEX into the thread-local OnceCell on the first use in the current thread
+pub(crate) fn compiled_regex_clone_cell<T>(f: impl FnOnce(&regex::Regex) -> T) -> T {
+    COMPILED_REGEX_CLONE_CELL.with(|cell| f(cell.get_or_init(|| COMPILED_REGEX.clone())))
 }
 
-#[test]
-fn bad_rust_local_test() {
-    let compiled_regex = regex::Regex::new(LONG_REGEX).unwrap();
-    let is_match = compiled_regex.is_match(TEST_EMAIL);
-    assert!(is_match);
+/// OnceLock has no initializer of its own, so it has to be passed on every access
+pub(crate) fn compiled_regex_once_lock() -> &'static regex::Regex {
+    COMPILED_REGEX_ONCE_LOCK.get_or_init(|| regex::Regex::new(LONG_REGEX).unwrap())
 }
 
 /// The regex is compiled within lazy_static declared in a separate module
 /// and used by a function within that module called repeatedly within the loop
-#[bench]
 fn lazy_static_inner(b: &mut Bencher) {
     b.iter(|| {
         let is_match = inner::lazy_static_local(TEST_EMAIL);
-        test::black_box(is_match);
+        black_box(is_match);
     });
 }
 
-#[test]
 fn lazy_static_inner_test() {
     let is_match = inner::lazy_static_local(TEST_EMAIL);
     assert!(is_match);
@@ -102,15 +80,13 @@ fn lazy_static_inner_test() {
 
 /// The regex is compiled within lazy_static declared in a separate module
 /// placed in an external file. It is expected to be compiled once only.
-#[bench]
 fn lazy_static_external_mod(b: &mut Bencher) {
     b.iter(|| {
         let is_match = external_mod::lazy_static_external(TEST_EMAIL);
-        test::black_box(is_match);
+        black_box(is_match);
     });
 }
 
-#[test]
 fn lazy_static_external_mod_test() {
     let is_match = external_mod::lazy_static_external(TEST_EMAIL);
     assert!(is_match);
@@ -118,15 +94,13 @@ fn lazy_static_external_mod_test() {
 
 /// The regex is compiled within lazy_static declared at the root module
 /// and is used from a mod placed in an external file.
-#[bench]
 fn lazy_static_backref(b: &mut Bencher) {
     b.iter(|| {
         let is_match = external_mod::lazy_static_backref(TEST_EMAIL);
-        test::black_box(is_match);
+        black_box(is_match);
     });
 }
 
-#[test]
 fn lazy_static_backref_test() {
     let is_match = external_mod::lazy_static_backref(TEST_EMAIL);
     assert!(is_match);
@@ -134,26 +108,192 @@ fn lazy_static_backref_test() {
 
 /// The regex is compiled within lazy_static at the module level
 /// and is re-initialized within the loop
-#[bench]
 fn lazy_static_reinit(b: &mut Bencher) {
     b.iter(|| {
         lazy_static::initialize(&COMPILED_REGEX);
         let is_match = COMPILED_REGEX.is_match(TEST_EMAIL);
-        test::black_box(is_match);
+        black_box(is_match);
     });
 }
 
-#[test]
 fn lazy_static_reinit_test() {
     lazy_static::initialize(&COMPILED_REGEX);
     let is_match = COMPILED_REGEX.is_match(TEST_EMAIL);
     assert!(is_match);
 }
 
+fn main() {
+    let matrix_benches = matrix::benches();
+    let mut benches: Vec<(&str, BenchFn)> = vec![
+        ("async_lazy_local", async_lazy::async_lazy_local),
+        (
+            "async_lazy_static_local",
+            async_lazy::async_lazy_static_local,
+        ),
+        (
+            "cold_start_hand_rolled_lazy",
+            cold_start::cold_start_hand_rolled_lazy,
+        ),
+        (
+            "cold_start_lazy_static_inner",
+            cold_start::cold_start_lazy_static_inner,
+        ),
+        (
+            "cold_start_lazy_static_local",
+            cold_start::cold_start_lazy_static_local,
+        ),
+        (
+            "cold_start_once_cell_lazy",
+            cold_start::cold_start_once_cell_lazy,
+        ),
+        (
+            "cold_start_once_cell_unsync_thread_local",
+            cold_start::cold_start_once_cell_unsync_thread_local,
+        ),
+        #[cfg(feature = "spin")]
+        ("cold_start_spin_lazy", cold_start::cold_start_spin_lazy),
+        (
+            "cold_start_std_lazy_lock",
+            cold_start::cold_start_std_lazy_lock,
+        ),
+        (
+            "cold_start_std_once_lock",
+            cold_start::cold_start_std_once_lock,
+        ),
+        (
+            "cold_start_vanilla_rust_local",
+            cold_start::cold_start_vanilla_rust_local,
+        ),
+        ("contention_epoch_lazy", contention::contention_epoch_lazy),
+        (
+            "contention_hand_rolled_lazy",
+            contention::contention_hand_rolled_lazy,
+        ),
+        (
+            "contention_lazy_static_inner",
+            contention::contention_lazy_static_inner,
+        ),
+        (
+            "contention_lazy_static_local",
+            contention::contention_lazy_static_local,
+        ),
+        (
+            "contention_once_cell_lazy",
+            contention::contention_once_cell_lazy,
+        ),
+        (
+            "contention_once_cell_unsync_thread_local",
+            contention::contention_once_cell_unsync_thread_local,
+        ),
+        (
+            "contention_reloadable_lazy",
+            contention::contention_reloadable_lazy,
+        ),
+        #[cfg(feature = "spin")]
+        ("contention_spin_lazy", contention::contention_spin_lazy),
+        (
+            "contention_std_lazy_lock",
+            contention::contention_std_lazy_lock,
+        ),
+        (
+            "contention_std_once_lock",
+            contention::contention_std_once_lock,
+        ),
+        (
+            "contention_thread_local_clone",
+            contention::contention_thread_local_clone,
+        ),
+        (
+            "contention_thread_local_once_cell",
+            contention::contention_thread_local_once_cell,
+        ),
+        ("epoch_lazy", reload::epoch_lazy),
+        ("epoch_lazy_reloading", reload::epoch_lazy_reloading),
+        ("lazy_static_backref", lazy_static_backref),
+        ("lazy_static_external_mod", lazy_static_external_mod),
+        ("lazy_static_inner", lazy_static_inner),
+        ("lazy_static_reinit", lazy_static_reinit),
+        ("race_hand_rolled_lazy", race::race_hand_rolled_lazy),
+        ("race_lazy_static_local", race::race_lazy_static_local),
+        ("race_once_cell_lazy", race::race_once_cell_lazy),
+        #[cfg(feature = "spin")]
+        ("race_spin_lazy", race::race_spin_lazy),
+        ("race_std_lazy_lock", race::race_std_lazy_lock),
+        ("reloadable_lazy", reload::reloadable_lazy),
+        (
+            "reloadable_lazy_reloading",
+            reload::reloadable_lazy_reloading,
+        ),
+        ("retry_lazy", fallible::retry_lazy),
+        ("retry_lazy_error", fallible::retry_lazy_error),
+        ("try_lazy", fallible::try_lazy),
+        ("try_lazy_error", fallible::try_lazy_error),
+    ];
+    benches.extend(
+        matrix_benches
+            .iter()
+            .map(|(name, bench)| (name.as_str(), *bench)),
+    );
+
+    let matrix_tests = matrix::tests();
+    let mut tests: Vec<(&str, TestFn)> = vec![
+        ("async_lazy_local_test", async_lazy::async_lazy_local_test),
+        (
+            "async_lazy_static_local_test",
+            async_lazy::async_lazy_static_local_test,
+        ),
+        ("async_lazy_tasks_test", async_lazy::async_lazy_tasks_test),
+        ("epoch_lazy_test", reload::epoch_lazy_test),
+        ("lazy_static_backref_test", lazy_static_backref_test),
+        (
+            "lazy_static_external_mod_test",
+            lazy_static_external_mod_test,
+        ),
+        ("lazy_static_inner_test", lazy_static_inner_test),
+        ("lazy_static_reinit_test", lazy_static_reinit_test),
+        (
+            "poisoning_hand_rolled_retry_test",
+            poisoning::poisoning_hand_rolled_retry_test,
+        ),
+        (
+            "poisoning_hand_rolled_test",
+            poisoning::poisoning_hand_rolled_test,
+        ),
+        (
+            "poisoning_lazy_static_test",
+            poisoning::poisoning_lazy_static_test,
+        ),
+        (
+            "poisoning_once_cell_test",
+            poisoning::poisoning_once_cell_test,
+        ),
+        (
+            "poisoning_std_lazy_lock_test",
+            poisoning::poisoning_std_lazy_lock_test,
+        ),
+        (
+            "poisoning_std_once_lock_test",
+            poisoning::poisoning_std_once_lock_test,
+        ),
+        ("reloadable_lazy_test", reload::reloadable_lazy_test),
+        ("retry_lazy_error_test", fallible::retry_lazy_error_test),
+        ("retry_lazy_test", fallible::retry_lazy_test),
+        ("try_lazy_error_test", fallible::try_lazy_error_test),
+        ("try_lazy_test", fallible::try_lazy_test),
+    ];
+    tests.extend(
+        matrix_tests
+            .iter()
+            .map(|(name, test)| (name.as_str(), *test)),
+    );
+
+    harness::main(&benches, &tests);
+}
+
 mod inner {
 
     lazy_static! {
-        static ref COMPILED_REGEX_INNER: regex::Regex =
+        pub(crate) static ref COMPILED_REGEX_INNER: regex::Regex =
             regex::Regex::new(super::LONG_REGEX).unwrap();
     }
 
@@ -0,0 +1,249 @@
+//! Every single-threaded strategy run with every [`Workload`], generated by [`matrix!`].
+//!
+//! Each combination gets a bench, a correctness test and a registry entry. The benches are named
+//! `strategy/workload`, e.g. `once_cell_lazy/lookup_table`, except for [`DEFAULT_WORKLOAD`]
+//! that keeps the plain strategy names the regex benches always had, e.g. `once_cell_lazy`.
+//! The tests are named the same way with `_test` after the strategy.
+//!
+//! A new strategy or workload is a single line in the [`matrix!`] invocation at the bottom.
+
+use rust_benchmarks::corpus::{Corpus, Emails, LongText, NearMisses, Unicode};
+use rust_benchmarks::harness::export::DEFAULT_WORKLOAD;
+use rust_benchmarks::harness::{BenchFn, TestFn};
+use rust_benchmarks::workload::{
+    Config, CrcTable, EmailBytes, EmailCaptures, EmailFind, EmailFindIter, EmailRegex, EmailSet,
+    LookupTable, Trivial, Workload,
+};
+
+/// The name of a bench or test of `strategy` with `workload`
+fn name(strategy: &str, workload: &str) -> String {
+    if workload == DEFAULT_WORKLOAD {
+        strategy.to_owned()
+    } else {
+        format!("{strategy}/{workload}")
+    }
+}
+
+/// Generates a module per strategy with a module per workload in it. Each workload module holds
+/// `with`, which passes the value stored by the strategy to a closure, and the `bench` and `test`
+/// that call it. [`benches`] and [`tests`] list them all for the harness.
+///
+/// The strategies are one of:
+/// * `vanilla`: the value is built once within the bench function, outside of the loop
+/// * `bad`: the value is built on every iteration
+/// * `lazy_static`, `declare_lazy`: the value is declared within the macro of the same name
+/// * `sync(Type)`: a `static` of a lazy type with `Type::new(init)` and `Deref`, e.g. `LazyLock`
+/// * `once_lock`: a `static OnceLock` with the initializer passed on every access
+/// * `unsync(Type)`: same as `sync`, but in `thread_local!`
+/// * `thread_local_clone`, `thread_local_once_cell`: a clone of a `lazy_static!` value is stored
+///   in `thread_local!` directly or in a const-initialized `OnceCell`
+macro_rules! matrix {
+    (
+        strategies {
+            $(
+                $(#[doc = $doc:literal])*
+                $(#[cfg($cfg:meta)])?
+                $strategy:ident: $kind:ident $(($($arg:tt)*))?,
+            )*
+        }
+        workloads $workloads:tt
+    ) => {
+        $(
+            matrix!(@strategy [$(#[doc = $doc])*] [$($cfg)?] $strategy $kind [$($($arg)*)?] $workloads);
+        )*
+
+        /// The benches of every strategy with every workload
+        pub(crate) fn benches() -> Vec<(String, BenchFn)> {
+            [$($(#[cfg($cfg)])? $strategy::benches(),)*].concat()
+        }
+
+        /// The tests of every strategy with every workload
+        pub(crate) fn tests() -> Vec<(String, TestFn)> {
+            [$($(#[cfg($cfg)])? $strategy::tests(),)*].concat()
+        }
+    };
+
+    (
+        @strategy [$(#[$doc:meta])*] [$($cfg:meta)?] $strategy:ident $kind:ident $args:tt
+        { $($module:ident: $workload:ty,)* }
+    ) => {
+        $(#[$doc])*
+        $(#[cfg($cfg)])?
+        pub(crate) mod $strategy {
+            use super::*;
+
+            $(
+                pub(crate) mod $module {
+                    use super::*;
+                    use rust_benchmarks::harness::Bencher;
+                    use std::hint::black_box;
+
+                    type W = $workload;
+                    type Value = <W as Workload>::Value;
+                    type Cursor = <W as Workload>::Cursor;
+
+                    matrix!(@with $kind $args);
+                    matrix!(@bench $kind);
+
+                    pub(crate) fn test() {
+                        let mut cursor = Cursor::default();
+                        assert!(with(|value| W::verify(&W::run(value, &mut cursor))));
+                    }
+                }
+            )*
+
+            pub(crate) fn benches() -> Vec<(String, BenchFn)> {
+                vec![$((name(stringify!($strategy), <$workload>::NAME), $module::bench as BenchFn),)*]
+            }
+
+            pub(crate) fn tests() -> Vec<(String, TestFn)> {
+                vec![$((
+                    name(concat!(stringify!($strategy), "_test"), <$workload>::NAME),
+                    $module::test as TestFn,
+                ),)*]
+            }
+        }
+    };
+
+    (@with vanilla []) => {
+        matrix!(@with bad []);
+    };
+    (@with bad []) => {
+        #[inline]
+        fn with<R>(f: impl FnOnce(&Value) -> R) -> R {
+            f(&W::build())
+        }
+    };
+    (@with lazy_static []) => {
+        #[inline]
+        fn with<R>(f: impl FnOnce(&Value) -> R) -> R {
+            lazy_static! {
+                static ref VALUE: Value = W::build();
+            }
+            f(&VALUE)
+        }
+    };
+    (@with declare_lazy []) => {
+        #[inline]
+        fn with<R>(f: impl FnOnce(&Value) -> R) -> R {
+            rust_benchmarks::declare_lazy! {
+                static ref VALUE: Value = W::build();
+            }
+            f(&VALUE)
+        }
+    };
+    (@with sync [$($lazy:tt)*]) => {
+        #[inline]
+        fn with<R>(f: impl FnOnce(&Value) -> R) -> R {
+            static VALUE: $($lazy)*<Value> = $($lazy)*::new(W::build);
+            f(&VALUE)
+        }
+    };
+    (@with once_lock []) => {
+        #[inline]
+        fn with<R>(f: impl FnOnce(&Value) -> R) -> R {
+            static VALUE: std::sync::OnceLock<Value> = std::sync::OnceLock::new();
+            f(VALUE.get_or_init(W::build))
+        }
+    };
+    (@with unsync [$($lazy:tt)*]) => {
+        #[inline]
+        fn with<R>(f: impl FnOnce(&Value) -> R) -> R {
+            thread_local! {
+                static VALUE: $($lazy)*<Value> = $($lazy)*::new(W::build);
+            }
+            VALUE.with(|value| f(value))
+        }
+    };
+    (@with thread_local_clone []) => {
+        #[inline]
+        fn with<R>(f: impl FnOnce(&Value) -> R) -> R {
+            lazy_static! {
+                static ref SHARED: Value = W::build();
+            }
+            thread_local! {
+                static VALUE: Value = SHARED.clone();
+            }
+            VALUE.with(f)
+        }
+    };
+    (@with thread_local_once_cell []) => {
+        #[inline]
+        fn with<R>(f: impl FnOnce(&Value) -> R) -> R {
+            lazy_static! {
+                static ref SHARED: Value = W::build();
+            }
+            thread_local! {
+                static VALUE: std::cell::OnceCell<Value> = const { std::cell::OnceCell::new() };
+            }
+            VALUE.with(|cell| f(cell.get_or_init(|| SHARED.clone())))
+        }
+    };
+
+    (@bench vanilla) => {
+        pub(crate) fn bench(b: &mut Bencher) {
+            let value = W::build();
+            let mut cursor = Cursor::default();
+            b.iter(|| {
+                let output = W::run(&value, &mut cursor);
+                black_box(output);
+            });
+        }
+    };
+    (@bench $kind:ident) => {
+        pub(crate) fn bench(b: &mut Bencher) {
+            let mut cursor = Cursor::default();
+            b.iter(|| {
+                let output = with(|value| W::run(value, &mut cursor));
+                black_box(output);
+            });
+        }
+    };
+}
+
+matrix! {
+    strategies {
+        /// The value is built once within the bench function, the cost of the payload without any lazy static
+        vanilla_rust_local: vanilla,
+        /// The value is built within the bench function loop, which is obviously inefficient,
+        /// but we do it to show the cost of building it
+        bad_rust_local: bad,
+        /// The value is built within lazy_static
+        lazy_static_local: lazy_static,
+        /// The value is built within declare_lazy!, the lazy_static look-alike on top of the in-repo Lazy
+        declare_lazy_local: declare_lazy,
+        /// The value is built once by once_cell::sync::Lazy on the first use within the loop
+        once_cell_lazy: sync(once_cell::sync::Lazy),
+        /// The value is built once by the in-repo rust_benchmarks::lazy::Lazy
+        hand_rolled_lazy: sync(rust_benchmarks::lazy::Lazy),
+        /// The value is built once by the no_std rust_benchmarks::spin::SpinLazy
+        #[cfg(feature = "spin")]
+        spin_lazy: sync(rust_benchmarks::spin::SpinLazy),
+        /// The value is built once by std::sync::LazyLock, the std version of once_cell::sync::Lazy
+        std_lazy_lock: sync(std::sync::LazyLock),
+        /// The value is built once by std::sync::OnceLock::get_or_init
+        std_once_lock: once_lock,
+        /// The value is built once per thread by once_cell::unsync::Lazy stored in thread_local!
+        once_cell_unsync_thread_local: unsync(once_cell::unsync::Lazy),
+        /// The value built by lazy_static is cloned into thread_local! storage and accessed via LocalKey::with
+        thread_local_clone: thread_local_clone,
+        /// The value built by lazy_static is cloned into a const-initialized thread-local OnceCell
+        thread_local_once_cell: thread_local_once_cell,
+    }
+    workloads {
+        email_regex: EmailRegex,
+        regex_find: EmailFind,
+        regex_captures: EmailCaptures,
+        regex_find_iter: EmailFindIter,
+        regex_set: EmailSet,
+        regex_bytes: EmailBytes,
+        lookup_table: LookupTable,
+        config: Config,
+        crc_table: CrcTable,
+        trivial: Trivial,
+        corpus_emails: Corpus<Emails>,
+        corpus_near_misses: Corpus<NearMisses>,
+        corpus_long_text: Corpus<LongText>,
+        corpus_unicode: Corpus<Unicode>,
+    }
+}
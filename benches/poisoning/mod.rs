@@ -0,0 +1,142 @@
+//! What happens after the initializer of a lazy static panics.
+//! Every static below is initialized by `compile_or_panic`, which panics on the first call
+//! and compiles the regex on the next ones, so a retry would succeed.
+//!
+//! * `lazy_static!`, `once_cell::sync::Lazy`, `LazyLock` and `Lazy` are poisoned:
+//!   every later access panics without calling the initializer again
+//! * `OnceLock::get_or_init` and `Lazy::with_retry` call the initializer again and succeed
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{LazyLock, OnceLock};
+
+use rust_benchmarks::lazy::{Lazy, Retry};
+
+use super::{LONG_REGEX, TEST_EMAIL};
+
+/// The message of the panic in the first call to `compile_or_panic`
+const FIRST_ATTEMPT: &str = "the first attempt to compile the regex failed";
+
+/// Panics on the first call, compiles `LONG_REGEX` on the next ones. `count` is the number of calls.
+fn compile_or_panic(count: &AtomicUsize) -> regex::Regex {
+    if count.fetch_add(1, Ordering::SeqCst) == 0 {
+        panic!("{FIRST_ATTEMPT}");
+    }
+    regex::Regex::new(LONG_REGEX).unwrap()
+}
+
+/// Calls `access` and returns the panic message if it panicked.
+/// The panic hook is silenced for the duration of the call, so the expected panics
+/// don't clutter the test output. The harness runs tests one at a time, so no other
+/// test can panic while the hook is swapped out.
+fn catch_panic(access: impl FnOnce() -> bool) -> Result<bool, String> {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let result = panic::catch_unwind(AssertUnwindSafe(access));
+    panic::set_hook(hook);
+
+    result.map_err(|payload| panic_message(&*payload))
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "<non-string panic payload>".to_string()
+    }
+}
+
+/// Asserts that the panic in the first access poisons the static for good
+fn assert_poisoned(count: &AtomicUsize, access: impl Fn() -> bool) {
+    assert_eq!(catch_panic(&access), Err(FIRST_ATTEMPT.to_string()));
+
+    for _ in 0..2 {
+        let message = catch_panic(&access).expect_err("a poisoned static must panic");
+        assert!(message.contains("poisoned"), "unexpected panic: {message}");
+    }
+
+    // the initializer was not called again
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+}
+
+/// Asserts that the access after the panic calls the initializer again and succeeds
+fn assert_retried(count: &AtomicUsize, access: impl Fn() -> bool) {
+    assert_eq!(catch_panic(&access), Err(FIRST_ATTEMPT.to_string()));
+    assert_eq!(catch_panic(&access), Ok(true));
+    assert_eq!(catch_panic(&access), Ok(true));
+
+    // the second attempt succeeded and the value was cached
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+}
+
+static LAZY_STATIC_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static! {
+    static ref COMPILED_REGEX_POISONED: regex::Regex = compile_or_panic(&LAZY_STATIC_COUNT);
+}
+
+static ONCE_CELL_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+static COMPILED_REGEX_POISONED_ONCE_CELL: once_cell::sync::Lazy<regex::Regex> =
+    once_cell::sync::Lazy::new(|| compile_or_panic(&ONCE_CELL_COUNT));
+
+static LAZY_LOCK_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+static COMPILED_REGEX_POISONED_LAZY_LOCK: LazyLock<regex::Regex> =
+    LazyLock::new(|| compile_or_panic(&LAZY_LOCK_COUNT));
+
+static ONCE_LOCK_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+static COMPILED_REGEX_POISONED_ONCE_LOCK: OnceLock<regex::Regex> = OnceLock::new();
+
+static HAND_ROLLED_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+static COMPILED_REGEX_POISONED_HAND_ROLLED: Lazy<regex::Regex> =
+    Lazy::new(|| compile_or_panic(&HAND_ROLLED_COUNT));
+
+static HAND_ROLLED_RETRY_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+static COMPILED_REGEX_POISONED_HAND_ROLLED_RETRY: Lazy<regex::Regex, fn() -> regex::Regex, Retry> =
+    Lazy::with_retry(|| compile_or_panic(&HAND_ROLLED_RETRY_COUNT));
+
+pub(crate) fn poisoning_lazy_static_test() {
+    assert_poisoned(&LAZY_STATIC_COUNT, || {
+        COMPILED_REGEX_POISONED.is_match(TEST_EMAIL)
+    });
+}
+
+pub(crate) fn poisoning_once_cell_test() {
+    assert_poisoned(&ONCE_CELL_COUNT, || {
+        COMPILED_REGEX_POISONED_ONCE_CELL.is_match(TEST_EMAIL)
+    });
+}
+
+pub(crate) fn poisoning_std_lazy_lock_test() {
+    assert_poisoned(&LAZY_LOCK_COUNT, || {
+        COMPILED_REGEX_POISONED_LAZY_LOCK.is_match(TEST_EMAIL)
+    });
+}
+
+/// Unlike `LazyLock`, `OnceLock` gets a new initializer on every call, so it can retry
+pub(crate) fn poisoning_std_once_lock_test() {
+    assert_retried(&ONCE_LOCK_COUNT, || {
+        COMPILED_REGEX_POISONED_ONCE_LOCK
+            .get_or_init(|| compile_or_panic(&ONCE_LOCK_COUNT))
+            .is_match(TEST_EMAIL)
+    });
+}
+
+pub(crate) fn poisoning_hand_rolled_test() {
+    assert_poisoned(&HAND_ROLLED_COUNT, || {
+        COMPILED_REGEX_POISONED_HAND_ROLLED.is_match(TEST_EMAIL)
+    });
+}
+
+pub(crate) fn poisoning_hand_rolled_retry_test() {
+    assert_retried(&HAND_ROLLED_RETRY_COUNT, || {
+        COMPILED_REGEX_POISONED_HAND_ROLLED_RETRY.is_match(TEST_EMAIL)
+    });
+}
@@ -0,0 +1,68 @@
+//! 64 threads racing to initialize the same uninitialized static in a fresh process.
+//! The initializers count their runs in `INIT_COUNT` to prove that only one of the threads
+//! compiled the regex and the rest waited for it.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::LazyLock;
+
+use rust_benchmarks::harness::Bencher;
+use rust_benchmarks::lazy::Lazy;
+
+use super::{LONG_REGEX, TEST_EMAIL};
+
+/// The number of times any of the initializers below ran in this process
+static INIT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+fn compile_counted() -> regex::Regex {
+    INIT_COUNT.fetch_add(1, Ordering::SeqCst);
+    regex::Regex::new(LONG_REGEX).unwrap()
+}
+
+lazy_static! {
+    static ref COMPILED_REGEX_RACE: regex::Regex = compile_counted();
+}
+
+static COMPILED_REGEX_RACE_ONCE_CELL: once_cell::sync::Lazy<regex::Regex> =
+    once_cell::sync::Lazy::new(compile_counted);
+
+static COMPILED_REGEX_RACE_LAZY_LOCK: LazyLock<regex::Regex> = LazyLock::new(compile_counted);
+
+static COMPILED_REGEX_RACE_HAND_ROLLED: Lazy<regex::Regex> = Lazy::new(compile_counted);
+
+#[cfg(feature = "spin")]
+static COMPILED_REGEX_RACE_SPIN: rust_benchmarks::spin::SpinLazy<regex::Regex> =
+    rust_benchmarks::spin::SpinLazy::new(compile_counted);
+
+/// The regex is compiled within lazy_static
+pub(crate) fn race_lazy_static_local(b: &mut Bencher) {
+    b.iter_race(&INIT_COUNT, || COMPILED_REGEX_RACE.is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled by once_cell::sync::Lazy
+pub(crate) fn race_once_cell_lazy(b: &mut Bencher) {
+    b.iter_race(&INIT_COUNT, || {
+        COMPILED_REGEX_RACE_ONCE_CELL.is_match(TEST_EMAIL)
+    });
+}
+
+/// The regex is compiled by std::sync::LazyLock
+pub(crate) fn race_std_lazy_lock(b: &mut Bencher) {
+    b.iter_race(&INIT_COUNT, || {
+        COMPILED_REGEX_RACE_LAZY_LOCK.is_match(TEST_EMAIL)
+    });
+}
+
+/// The regex is compiled by the in-repo rust_benchmarks::lazy::Lazy, the waiting threads are parked by Once
+pub(crate) fn race_hand_rolled_lazy(b: &mut Bencher) {
+    b.iter_race(&INIT_COUNT, || {
+        COMPILED_REGEX_RACE_HAND_ROLLED.is_match(TEST_EMAIL)
+    });
+}
+
+/// The regex is compiled by the no_std rust_benchmarks::spin::SpinLazy, the waiting threads spin
+#[cfg(feature = "spin")]
+pub(crate) fn race_spin_lazy(b: &mut Bencher) {
+    b.iter_race(&INIT_COUNT, || {
+        COMPILED_REGEX_RACE_SPIN.is_match(TEST_EMAIL)
+    });
+}
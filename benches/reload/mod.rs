@@ -0,0 +1,110 @@
+//! Lazy statics that can swap the compiled regex at run time, e.g. after a config change.
+//! Compare `reloadable_lazy` and `epoch_lazy` with `hand_rolled_lazy` to see the cost of
+//! the read guard, and the `_reloading` variants to see how much a concurrent reload slows
+//! down the readers.
+
+use std::hint::black_box;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use rust_benchmarks::harness::Bencher;
+use rust_benchmarks::lazy::{EpochLazy, ReloadableLazy};
+
+use super::{COMPILED_REGEX, LONG_REGEX, TEST_EMAIL};
+
+/// How often the background thread of the `_reloading` benches replaces the regex
+const RELOAD_INTERVAL: Duration = Duration::from_millis(1);
+
+pub(crate) static COMPILED_REGEX_RELOADABLE: ReloadableLazy<regex::Regex> =
+    ReloadableLazy::new(|| regex::Regex::new(LONG_REGEX).unwrap());
+
+pub(crate) static COMPILED_REGEX_EPOCH: EpochLazy<regex::Regex> =
+    EpochLazy::new(|| regex::Regex::new(LONG_REGEX).unwrap());
+
+/// Runs `bench` while another thread keeps replacing the regex with a clone of COMPILED_REGEX
+fn while_reloading(reload: impl Fn(regex::Regex) + Sync, bench: impl FnOnce()) {
+    let done = AtomicBool::new(false);
+    thread::scope(|s| {
+        s.spawn(|| {
+            while !done.load(Ordering::Relaxed) {
+                reload(COMPILED_REGEX.clone());
+                thread::sleep(RELOAD_INTERVAL);
+            }
+        });
+
+        bench();
+        done.store(true, Ordering::Relaxed);
+    });
+}
+
+/// Checks the regex can be swapped for a different one and back
+fn assert_reloads<G: std::ops::Deref<Target = regex::Regex>>(
+    get: impl Fn() -> G,
+    reload: impl Fn(regex::Regex),
+) {
+    assert!(get().is_match(TEST_EMAIL));
+
+    reload(regex::Regex::new("^[0-9]+$").unwrap());
+    assert!(!get().is_match(TEST_EMAIL));
+    assert!(get().is_match("42"));
+
+    reload(COMPILED_REGEX.clone());
+    assert!(get().is_match(TEST_EMAIL));
+}
+
+/// The regex is compiled by ReloadableLazy and read under a RwLock read lock
+pub(crate) fn reloadable_lazy(b: &mut Bencher) {
+    b.iter(|| {
+        let is_match = COMPILED_REGEX_RELOADABLE.get().is_match(TEST_EMAIL);
+        black_box(is_match);
+    });
+}
+
+pub(crate) fn reloadable_lazy_test() {
+    assert_reloads(
+        || COMPILED_REGEX_RELOADABLE.get(),
+        |re| COMPILED_REGEX_RELOADABLE.reload(re),
+    );
+}
+
+/// The regex is compiled by EpochLazy and read through an atomic pointer guarded by an epoch counter
+pub(crate) fn epoch_lazy(b: &mut Bencher) {
+    b.iter(|| {
+        let is_match = COMPILED_REGEX_EPOCH.get().is_match(TEST_EMAIL);
+        black_box(is_match);
+    });
+}
+
+pub(crate) fn epoch_lazy_test() {
+    assert_reloads(
+        || COMPILED_REGEX_EPOCH.get(),
+        |re| COMPILED_REGEX_EPOCH.reload(re),
+    );
+}
+
+/// Same as reloadable_lazy, while another thread reloads the regex every millisecond
+pub(crate) fn reloadable_lazy_reloading(b: &mut Bencher) {
+    while_reloading(
+        |re| COMPILED_REGEX_RELOADABLE.reload(re),
+        || {
+            b.iter(|| {
+                let is_match = COMPILED_REGEX_RELOADABLE.get().is_match(TEST_EMAIL);
+                black_box(is_match);
+            })
+        },
+    );
+}
+
+/// Same as epoch_lazy, while another thread reloads the regex every millisecond
+pub(crate) fn epoch_lazy_reloading(b: &mut Bencher) {
+    while_reloading(
+        |re| COMPILED_REGEX_EPOCH.reload(re),
+        || {
+            b.iter(|| {
+                let is_match = COMPILED_REGEX_EPOCH.get().is_match(TEST_EMAIL);
+                black_box(is_match);
+            })
+        },
+    );
+}
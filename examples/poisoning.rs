@@ -0,0 +1,80 @@
+//! Shows what each lazy static does after its initializer panics.
+//!
+//! Every initializer panics on the first call and returns 42 on the next ones.
+//! Run with `cargo run --example poisoning`.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{LazyLock, OnceLock};
+
+use lazy_static::lazy_static;
+use rust_benchmarks::lazy::{Lazy, Retry};
+
+/// Panics on the first call, returns 42 on the next ones
+fn panic_once(count: &AtomicUsize) -> u32 {
+    if count.fetch_add(1, Ordering::SeqCst) == 0 {
+        panic!("first attempt");
+    }
+    42
+}
+
+/// Accesses the static three times and prints the outcome of each access
+/// along with how many times the initializer ran
+fn demo(name: &str, count: &AtomicUsize, access: impl Fn() -> u32) {
+    let outcomes: Vec<String> = (0..3)
+        .map(|_| match panic::catch_unwind(AssertUnwindSafe(&access)) {
+            Ok(value) => format!("returned {value}"),
+            Err(_) => "panicked".to_string(),
+        })
+        .collect();
+
+    println!(
+        "{name:<24} {:<12} {:<12} {:<12} {}",
+        outcomes[0],
+        outcomes[1],
+        outcomes[2],
+        count.load(Ordering::SeqCst)
+    );
+}
+
+static LAZY_STATIC_COUNT: AtomicUsize = AtomicUsize::new(0);
+lazy_static! {
+    static ref LAZY_STATIC: u32 = panic_once(&LAZY_STATIC_COUNT);
+}
+
+static ONCE_CELL_COUNT: AtomicUsize = AtomicUsize::new(0);
+static ONCE_CELL: once_cell::sync::Lazy<u32> =
+    once_cell::sync::Lazy::new(|| panic_once(&ONCE_CELL_COUNT));
+
+static LAZY_LOCK_COUNT: AtomicUsize = AtomicUsize::new(0);
+static LAZY_LOCK: LazyLock<u32> = LazyLock::new(|| panic_once(&LAZY_LOCK_COUNT));
+
+static ONCE_LOCK_COUNT: AtomicUsize = AtomicUsize::new(0);
+static ONCE_LOCK: OnceLock<u32> = OnceLock::new();
+
+static HAND_ROLLED_COUNT: AtomicUsize = AtomicUsize::new(0);
+static HAND_ROLLED: Lazy<u32> = Lazy::new(|| panic_once(&HAND_ROLLED_COUNT));
+
+static HAND_ROLLED_RETRY_COUNT: AtomicUsize = AtomicUsize::new(0);
+static HAND_ROLLED_RETRY: Lazy<u32, fn() -> u32, Retry> =
+    Lazy::with_retry(|| panic_once(&HAND_ROLLED_RETRY_COUNT));
+
+fn main() {
+    // the panics are expected, the table below is the interesting part
+    panic::set_hook(Box::new(|_| {}));
+
+    println!(
+        "{:<24} {:<12} {:<12} {:<12} initializer calls",
+        "strategy", "1st", "2nd", "3rd"
+    );
+    demo("lazy_static!", &LAZY_STATIC_COUNT, || *LAZY_STATIC);
+    demo("once_cell::sync::Lazy", &ONCE_CELL_COUNT, || *ONCE_CELL);
+    demo("LazyLock", &LAZY_LOCK_COUNT, || *LAZY_LOCK);
+    demo("OnceLock::get_or_init", &ONCE_LOCK_COUNT, || {
+        *ONCE_LOCK.get_or_init(|| panic_once(&ONCE_LOCK_COUNT))
+    });
+    demo("Lazy::new", &HAND_ROLLED_COUNT, || *HAND_ROLLED);
+    demo("Lazy::with_retry", &HAND_ROLLED_RETRY_COUNT, || {
+        *HAND_ROLLED_RETRY
+    });
+}
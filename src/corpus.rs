@@ -0,0 +1,203 @@
+//! Inputs for the regex benches, loaded from the text files in `corpus/`, one input per line.
+//!
+//! Every file is a class of inputs that either all contain an email or all don't:
+//! * `emails.txt`: addresses alone, from the short and plain to the unusual but valid
+//! * `near_misses.txt`: almost addresses that fail somewhere in the middle of the regex
+//! * `long_text.txt`: paragraphs of up to a few KB with no address in them
+//! * `unicode.txt`: addresses within non-ASCII text
+//!
+//! [`Corpus<C>`] is a [`Workload`] that matches [`LONG_REGEX`](crate::workload::LONG_REGEX)
+//! against the inputs of the class `C`, the next input on every call, so a bench of it reports
+//! the average time per input of the class with one lazy access per input, same as a real caller.
+//! The inputs are taken from an [`InputCursor`] owned by the bench, so the only lazy access
+//! measured is the one of the strategy.
+
+use crate::workload::{EmailRegex, Workload};
+use std::fs;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::LazyLock;
+
+/// The directory the input files are loaded from
+pub const CORPUS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/corpus");
+
+/// A class of inputs loaded from a file in [`CORPUS_DIR`]
+pub trait InputClass: 'static {
+    /// The name in the bench names and the results, e.g. `corpus_emails`
+    const NAME: &'static str;
+    /// Whether `LONG_REGEX` finds an email in every input of the class, or in none of them
+    const MATCHES: bool;
+
+    /// The inputs, loaded on the first call
+    fn inputs() -> &'static Inputs;
+}
+
+/// The inputs of a class
+#[derive(Debug)]
+pub struct Inputs {
+    inputs: Vec<String>,
+}
+
+impl Inputs {
+    /// Loads the non-empty lines of the file at `path`
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        let inputs: Vec<String> = text
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_owned)
+            .collect();
+        if inputs.is_empty() {
+            return Err(format!("no inputs in {}", path.display()));
+        }
+        Ok(Self { inputs })
+    }
+
+    pub fn as_slice(&self) -> &[String] {
+        &self.inputs
+    }
+}
+
+/// Hands out the inputs of the class `C` one after another, starting over after the last one
+pub struct InputCursor<C> {
+    inputs: &'static [String],
+    next: usize,
+    class: PhantomData<C>,
+}
+
+impl<C: InputClass> Default for InputCursor<C> {
+    /// Loads the inputs on the first call for the class, not in the measured loop
+    fn default() -> Self {
+        Self {
+            inputs: C::inputs().as_slice(),
+            next: 0,
+            class: PhantomData,
+        }
+    }
+}
+
+impl<C> InputCursor<C> {
+    pub fn next_input(&mut self) -> &'static str {
+        let input = &self.inputs[self.next];
+        self.next = if self.next + 1 == self.inputs.len() {
+            0
+        } else {
+            self.next + 1
+        };
+        input
+    }
+}
+
+/// Declares an [`InputClass`] for each file, `corpus_` followed by the file stem is its name
+macro_rules! input_classes {
+    ($($(#[doc = $doc:literal])* $class:ident: $stem:literal, matches: $matches:literal;)*) => {$(
+        $(#[doc = $doc])*
+        pub struct $class;
+
+        impl InputClass for $class {
+            const NAME: &'static str = concat!("corpus_", $stem);
+            const MATCHES: bool = $matches;
+
+            fn inputs() -> &'static Inputs {
+                static INPUTS: LazyLock<Inputs> = LazyLock::new(|| {
+                    let path = Path::new(CORPUS_DIR).join(concat!($stem, ".txt"));
+                    Inputs::load(&path).unwrap_or_else(|e| panic!("{e}"))
+                });
+                &INPUTS
+            }
+        }
+    )*};
+}
+
+input_classes! {
+    /// `corpus/emails.txt`
+    Emails: "emails", matches: true;
+    /// `corpus/near_misses.txt`
+    NearMisses: "near_misses", matches: false;
+    /// `corpus/long_text.txt`
+    LongText: "long_text", matches: false;
+    /// `corpus/unicode.txt`
+    Unicode: "unicode", matches: true;
+}
+
+/// `LONG_REGEX` matched against the next input of the class `C` on every call
+pub struct Corpus<C>(PhantomData<C>);
+
+impl<C: InputClass> Workload for Corpus<C> {
+    const NAME: &'static str = C::NAME;
+    type Value = regex::Regex;
+    type Output = bool;
+    type Cursor = InputCursor<C>;
+
+    fn build() -> regex::Regex {
+        EmailRegex::build()
+    }
+
+    fn run(regex: &regex::Regex, cursor: &mut InputCursor<C>) -> bool {
+        regex.is_match(cursor.next_input())
+    }
+
+    fn verify(is_match: &bool) -> bool {
+        *is_match == C::MATCHES
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check<C: InputClass>() {
+        let regex = EmailRegex::build();
+        for input in C::inputs().as_slice() {
+            assert_eq!(regex.is_match(input), C::MATCHES, "{}: {input}", C::NAME);
+        }
+        // the workload goes through all of them
+        let mut cursor = InputCursor::<C>::default();
+        for _ in C::inputs().as_slice() {
+            assert!(Corpus::<C>::verify(&Corpus::<C>::run(&regex, &mut cursor)));
+        }
+    }
+
+    #[test]
+    fn classes_test() {
+        check::<Emails>();
+        check::<NearMisses>();
+        check::<LongText>();
+        check::<Unicode>();
+
+        assert!(LongText::inputs()
+            .as_slice()
+            .iter()
+            .any(|input| input.len() > 4096));
+        assert!(Unicode::inputs()
+            .as_slice()
+            .iter()
+            .all(|input| !input.is_ascii()));
+    }
+
+    #[test]
+    fn next_input_test() {
+        let inputs = vec!["a".to_owned(), "b".to_owned()].leak();
+        let mut cursor = InputCursor::<Emails> {
+            inputs,
+            next: 0,
+            class: PhantomData,
+        };
+        let taken: Vec<&str> = (0..5).map(|_| cursor.next_input()).collect();
+        assert_eq!(taken, ["a", "b", "a", "b", "a"]);
+    }
+
+    #[test]
+    fn load_errors() {
+        let missing = Path::new(CORPUS_DIR).join("missing.txt");
+        assert!(Inputs::load(&missing)
+            .unwrap_err()
+            .starts_with("cannot read"));
+
+        let empty = std::env::temp_dir().join(format!("corpus-test-{}.txt", std::process::id()));
+        fs::write(&empty, "\n\n").unwrap();
+        assert!(Inputs::load(&empty).unwrap_err().starts_with("no inputs"));
+        fs::remove_file(&empty).unwrap();
+    }
+}
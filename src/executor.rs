@@ -0,0 +1,230 @@
+//! A tiny single-threaded executor to drive [`AsyncLazy`](crate::lazy::AsyncLazy) in tests
+//! and benches without pulling in an async runtime.
+//!
+//! * [`block_on`] runs one future to completion on the current thread
+//! * [`LocalExecutor`] runs several tasks on the current thread, interleaving them at every `.await`
+//!   that returns `Pending`, which is enough to have them race for the same lazy value
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+/// Wakes the thread blocked in [`block_on`]
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs `future` to completion, parking the current thread while it is pending
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+/// Returns `Pending` once and wakes itself, so other tasks get a chance to run
+pub async fn yield_now() {
+    let mut yielded = false;
+    std::future::poll_fn(|cx| {
+        if yielded {
+            Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+/// The indices of the tasks that were woken and have to be polled again
+#[derive(Default)]
+struct ReadyQueue {
+    tasks: Mutex<VecDeque<usize>>,
+    thread: Mutex<Option<Thread>>,
+}
+
+/// Wakes one task of a [`LocalExecutor`]
+struct TaskWaker {
+    task: usize,
+    queue: Arc<ReadyQueue>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let mut tasks = self
+            .queue
+            .tasks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if !tasks.contains(&self.task) {
+            tasks.push_back(self.task);
+        }
+        drop(tasks);
+
+        if let Some(thread) = &*self
+            .queue
+            .thread
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+        {
+            thread.unpark();
+        }
+    }
+}
+
+type Task<'a> = Pin<Box<dyn Future<Output = ()> + 'a>>;
+
+/// Runs the spawned tasks on the current thread until all of them complete.
+///
+/// ```
+/// use rust_benchmarks::executor::{yield_now, LocalExecutor};
+/// use std::cell::Cell;
+///
+/// let counter = Cell::new(0);
+/// let executor = LocalExecutor::new();
+/// for _ in 0..3 {
+///     executor.spawn(async {
+///         yield_now().await;
+///         counter.set(counter.get() + 1);
+///     });
+/// }
+/// executor.run();
+/// assert_eq!(counter.get(), 3);
+/// ```
+#[derive(Default)]
+pub struct LocalExecutor<'a> {
+    /// `None` once a task is complete, the index is the task id used by `TaskWaker`
+    tasks: RefCell<Vec<Option<Task<'a>>>>,
+    queue: Arc<ReadyQueue>,
+}
+
+impl<'a> LocalExecutor<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a task that will be polled for the first time by [`run`](Self::run)
+    pub fn spawn(&self, future: impl Future<Output = ()> + 'a) {
+        let mut tasks = self.tasks.borrow_mut();
+        self.queue
+            .tasks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push_back(tasks.len());
+        tasks.push(Some(Box::pin(future)));
+    }
+
+    /// Polls the woken tasks in the order they were woken until all of them complete,
+    /// parking the current thread while none of them are ready
+    pub fn run(&self) {
+        *self
+            .queue
+            .thread
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(thread::current());
+
+        while self.tasks.borrow().iter().any(Option::is_some) {
+            let next = self
+                .queue
+                .tasks
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .pop_front();
+            let Some(task) = next else {
+                thread::park();
+                continue;
+            };
+
+            // taken out of the list while it is polled, so it can spawn more tasks
+            let Some(mut future) = self.tasks.borrow_mut()[task].take() else {
+                continue;
+            };
+
+            let waker = Waker::from(Arc::new(TaskWaker {
+                task,
+                queue: Arc::clone(&self.queue),
+            }));
+            if future
+                .as_mut()
+                .poll(&mut Context::from_waker(&waker))
+                .is_pending()
+            {
+                self.tasks.borrow_mut()[task] = Some(future);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn block_on_test() {
+        let value = block_on(async {
+            yield_now().await;
+            42
+        });
+        assert_eq!(value, 42);
+    }
+
+    #[test]
+    fn interleaves_tasks() {
+        let order = RefCell::new(Vec::new());
+        let executor = LocalExecutor::new();
+        for task in 0..2 {
+            let order = &order;
+            executor.spawn(async move {
+                for step in 0..2 {
+                    order.borrow_mut().push((task, step));
+                    yield_now().await;
+                }
+            });
+        }
+        executor.run();
+        assert_eq!(*order.borrow(), vec![(0, 0), (1, 0), (0, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn wakes_from_another_thread() {
+        let done = Cell::new(false);
+        let executor = LocalExecutor::new();
+        executor.spawn(async {
+            let (sender, receiver) = std::sync::mpsc::channel::<Waker>();
+            let handle = thread::spawn(move || receiver.recv().unwrap().wake());
+            let mut sender = Some(sender);
+            std::future::poll_fn(|cx| match sender.take() {
+                Some(sender) => {
+                    sender.send(cx.waker().clone()).unwrap();
+                    Poll::Pending
+                }
+                None => Poll::Ready(()),
+            })
+            .await;
+            handle.join().unwrap();
+            done.set(true);
+        });
+        executor.run();
+        assert!(done.get());
+    }
+}
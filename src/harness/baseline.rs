@@ -0,0 +1,126 @@
+//! Named baselines to compare bench runs against, e.g. before and after a toolchain update.
+//!
+//! `--save-baseline NAME` stores the results of the run in `<results dir>/baselines/NAME.json`,
+//! in the same format as [`export`](super::export). `--baseline NAME` compares every bench of the run
+//! with the same bench and thread count in that file using [`Comparison`] and prints the change of
+//! the median with its confidence interval.
+//!
+//! A bench _regresses_ if it is significantly slower and its median went up by more than the threshold,
+//! `--threshold PERCENT`, [`DEFAULT_THRESHOLD`] by default. The harness exits with code 1 if any bench
+//! regressed, so a CI job fails on it, and with 101 if any bench panicked, same as libtest.
+
+use super::export::{BenchResult, Report};
+use super::stats::Comparison;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The relative change of the median above which a significant slowdown is a regression, 5%
+pub const DEFAULT_THRESHOLD: f64 = 0.05;
+/// The subdirectory of the results directory the baselines are stored in
+const BASELINES_DIR: &str = "baselines";
+
+/// A bench of the current run compared to the baseline
+pub struct BaselineComparison<'a> {
+    pub result: &'a BenchResult,
+    /// `None` if the bench is not in the baseline
+    pub comparison: Option<Comparison>,
+}
+
+impl BaselineComparison<'_> {
+    /// The bench is significantly slower than in the baseline and its median went up by more than `threshold`
+    pub fn is_regression(&self, threshold: f64) -> bool {
+        self.comparison
+            .as_ref()
+            .is_some_and(|c| c.is_significant() && c.change > threshold)
+    }
+}
+
+/// The path of the baseline `name` in `results_dir`
+pub fn path(results_dir: &Path, name: &str) -> PathBuf {
+    results_dir.join(BASELINES_DIR).join(format!("{name}.json"))
+}
+
+/// Stores `report` as the baseline `name`, replacing the previous one. Returns the path of the file.
+pub fn save(report: &Report, results_dir: &Path, name: &str) -> io::Result<PathBuf> {
+    let path = path(results_dir, name);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, report.to_json())?;
+    Ok(path)
+}
+
+/// Loads the baseline `name` from `results_dir`
+pub fn load(results_dir: &Path, name: &str) -> Result<Report, String> {
+    let path = path(results_dir, name);
+    let json = fs::read_to_string(&path)
+        .map_err(|e| format!("cannot read baseline {name} from {}: {e}", path.display()))?;
+    Report::from_json(&json).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Compares every result of the current run with the same bench and thread count in `baseline`
+pub fn compare<'a>(baseline: &Report, results: &'a [BenchResult]) -> Vec<BaselineComparison<'a>> {
+    results
+        .iter()
+        .map(|result| {
+            let base = baseline
+                .results
+                .iter()
+                .find(|base| base.name == result.name && base.threads == result.threads);
+            BaselineComparison {
+                result,
+                comparison: base.map(|base| Comparison::new(&base.samples, &result.samples)),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::export::fixtures::{report, result, samples};
+
+    #[test]
+    fn detects_regressions() {
+        let baseline = report(vec![
+            result("lazy_static_local", 1, samples(30.0)),
+            result("once_cell_lazy", 1, samples(30.0)),
+            result("contention_hand_rolled_lazy", 4, samples(30.0)),
+        ]);
+        let current = [
+            // 10% slower
+            result("lazy_static_local", 1, samples(33.0)),
+            // 2% slower, significant but under the threshold
+            result("once_cell_lazy", 1, samples(30.6)),
+            // faster
+            result("contention_hand_rolled_lazy", 4, samples(20.0)),
+            // not in the baseline for this thread count
+            result("contention_hand_rolled_lazy", 8, samples(40.0)),
+        ];
+
+        let comparisons = compare(&baseline, &current);
+        let regressed: Vec<bool> = comparisons
+            .iter()
+            .map(|c| c.is_regression(DEFAULT_THRESHOLD))
+            .collect();
+        assert_eq!(regressed, [true, false, false, false]);
+        assert!(comparisons[1].comparison.unwrap().is_significant());
+        assert!(comparisons[3].comparison.is_none());
+        assert!(comparisons[0].is_regression(0.09));
+        assert!(!comparisons[0].is_regression(0.11));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = std::env::temp_dir().join(format!("baseline-test-{}", std::process::id()));
+        let report = report(vec![result("lazy_static_local", 1, samples(30.0))]);
+
+        let path = save(&report, &dir, "main").unwrap();
+        assert_eq!(path, dir.join("baselines").join("main.json"));
+        assert_eq!(load(&dir, "main").unwrap(), report);
+        assert!(load(&dir, "missing").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
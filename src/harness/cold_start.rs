@@ -0,0 +1,193 @@
+//! First-access latency measured in a fresh process per sample.
+//!
+//! A lazy static can only be initialized once per process, so the only way to sample its
+//! cold path repeatedly is to start a new process for every sample. The parent re-executes
+//! the bench binary with `--cold-start NAME`, the child runs bench `NAME` with timed calls
+//! and prints the results to stdout for the parent to collect.
+//!
+//! There are two kinds of measurements:
+//! * a single first access from one thread, see [`measure`]
+//! * [`RACE_THREADS`] threads released by a barrier at the same time racing to initialize
+//!   the same static, see [`measure_race`]
+
+use std::hint::black_box;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Barrier;
+use std::thread;
+use std::time::Instant;
+
+/// The number of child processes started per bench
+const SAMPLE_COUNT: usize = 30;
+/// The number of child processes started per race bench, each of them starts [`RACE_THREADS`] threads
+const RACE_SAMPLE_COUNT: usize = 20;
+/// The number of threads racing to initialize the same static
+pub const RACE_THREADS: usize = 64;
+/// The command line flag that turns the bench binary into a child process for a single bench
+pub(crate) const CHILD_FLAG: &str = "--cold-start";
+/// The prefix of the lines with the measured values in the child's stdout,
+/// followed by the name of the value and comma-separated nanoseconds,
+/// e.g. `cold-start:first-access:123456`
+const REPORT_PREFIX: &str = "cold-start:";
+/// The name of the value reported by [`report`]
+pub(crate) const FIRST_ACCESS: &str = "first-access";
+/// The names of the values reported by [`report_race`]
+pub(crate) const RACE_WALL: &str = "race-wall";
+pub(crate) const RACE_WAITS: &str = "race-waits";
+
+/// Timings collected from all the child processes of a race bench, all in nanoseconds
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct RaceSamples {
+    /// From the moment the threads are released until all of them got the value, one per process
+    pub wall: Vec<f64>,
+    /// How long each thread waited for the value, [`RACE_THREADS`] per process
+    pub waits: Vec<f64>,
+}
+
+/// The values printed by a child process
+pub struct ChildReport {
+    bench_name: String,
+    values: Vec<(String, Vec<f64>)>,
+}
+
+impl ChildReport {
+    fn parse(bench_name: &str, stdout: &str) -> Result<Self, String> {
+        let values = stdout
+            .lines()
+            .filter_map(|line| line.strip_prefix(REPORT_PREFIX))
+            .map(|line| {
+                let (key, values) = line
+                    .split_once(':')
+                    .ok_or_else(|| format!("invalid report line from {bench_name}: {line}"))?;
+                let values = values
+                    .split(',')
+                    .map(|v| v.trim().parse::<f64>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| format!("invalid value from {bench_name}: {e}"))?;
+                Ok((key.to_owned(), values))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self {
+            bench_name: bench_name.to_owned(),
+            values,
+        })
+    }
+
+    /// Values reported under the given name
+    pub fn get(&self, key: &str) -> Result<&[f64], String> {
+        self.values
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_slice())
+            .ok_or_else(|| {
+                format!(
+                    "the child process for {} did not report {key}",
+                    self.bench_name
+                )
+            })
+    }
+}
+
+/// Starts a child process per sample for the bench with the given name
+/// and returns the first-access latency in nanoseconds reported by each of them.
+pub fn measure(bench_name: &str) -> Result<Vec<f64>, String> {
+    (0..SAMPLE_COUNT)
+        .map(|_| Ok(run_child(bench_name)?.get(FIRST_ACCESS)?[0]))
+        .collect()
+}
+
+/// Starts a child process per sample for the race bench with the given name
+/// and collects the wall and per-thread wait times from all of them.
+pub fn measure_race(bench_name: &str) -> Result<RaceSamples, String> {
+    let mut samples = RaceSamples::default();
+    for _ in 0..RACE_SAMPLE_COUNT {
+        let report = run_child(bench_name)?;
+        samples.wall.extend(report.get(RACE_WALL)?);
+        samples.waits.extend(report.get(RACE_WAITS)?);
+    }
+    Ok(samples)
+}
+
+/// Re-executes the current binary with [`CHILD_FLAG`] and parses the values it reports
+pub fn run_child(bench_name: &str) -> Result<ChildReport, String> {
+    let exe =
+        std::env::current_exe().map_err(|e| format!("cannot locate the bench binary: {e}"))?;
+    let output = Command::new(exe)
+        .args([CHILD_FLAG, bench_name])
+        .output()
+        .map_err(|e| format!("cannot start a child process: {e}"))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "the child process for {bench_name} failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    ChildReport::parse(bench_name, &String::from_utf8_lossy(&output.stdout))
+}
+
+/// Called in the child process: times a single call to `f` and reports it to the parent
+pub(crate) fn report<T, F: FnOnce() -> T>(f: F) {
+    let start = Instant::now();
+    black_box(f());
+    let elapsed = start.elapsed();
+    print_values(FIRST_ACCESS, &[elapsed.as_nanos()]);
+}
+
+/// Called in the child process: releases [`RACE_THREADS`] threads calling `f` at the same time,
+/// reports how long each of them waited and panics if `init_count` is not 1 after the race.
+pub(crate) fn report_race<T, F: Fn() -> T + Sync>(f: F, init_count: &AtomicUsize) {
+    // the main thread is one of the parties so it can start the clock when all threads are ready
+    let barrier = Barrier::new(RACE_THREADS + 1);
+
+    let (waits, wall) = thread::scope(|s| {
+        let threads: Vec<_> = (0..RACE_THREADS)
+            .map(|_| {
+                s.spawn(|| {
+                    barrier.wait();
+                    let start = Instant::now();
+                    black_box(f());
+                    start.elapsed().as_nanos()
+                })
+            })
+            .collect();
+
+        barrier.wait();
+        let start = Instant::now();
+        let waits: Vec<_> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+        (waits, start.elapsed().as_nanos())
+    });
+
+    let init_count = init_count.load(Ordering::SeqCst);
+    assert_eq!(
+        init_count, 1,
+        "the initializer ran {init_count} times instead of once"
+    );
+
+    print_values(RACE_WALL, &[wall]);
+    print_values(RACE_WAITS, &waits);
+}
+
+fn print_values(key: &str, values: &[u128]) {
+    let values: Vec<_> = values.iter().map(u128::to_string).collect();
+    println!("{REPORT_PREFIX}{key}:{}", values.join(","));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn child_report_test() {
+        let stdout = "something else\ncold-start:first-access:123\ncold-start:race-waits:1,2,3\n";
+        let report = ChildReport::parse("bench", stdout).unwrap();
+        assert_eq!(report.get("first-access").unwrap(), &[123.0]);
+        assert_eq!(report.get("race-waits").unwrap(), &[1.0, 2.0, 3.0]);
+        assert!(report.get("race-wall").is_err());
+
+        assert!(ChildReport::parse("bench", "cold-start:first-access:abc").is_err());
+    }
+}
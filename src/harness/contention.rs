@@ -0,0 +1,111 @@
+//! Throughput measurements of a closure called from several threads at once.
+//!
+//! All threads wait on a barrier, hammer the closure for [`SAMPLE_TIME`] and report how many
+//! calls they made. Repeating it for 1, 2, 4 .. N threads gives a scaling curve: a lazy static
+//! that doesn't suffer from contention should show the total throughput growing with the thread count.
+
+use std::hint::black_box;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Barrier;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long all threads call the closure for in a single sample
+const SAMPLE_TIME: Duration = Duration::from_millis(50);
+/// The number of samples per thread count
+const SAMPLE_COUNT: usize = 20;
+/// The number of calls between checks of the stop flag, so the check itself is not measured
+const BATCH_SIZE: u64 = 256;
+
+/// Throughput samples for a single thread count
+#[derive(Clone, PartialEq, Debug)]
+pub struct ThreadSamples {
+    pub threads: usize,
+    /// Total calls per second across all threads, one value per sample
+    pub ops_per_sec: Vec<f64>,
+}
+
+/// Thread counts to measure: powers of 2 up to `max_threads` plus `max_threads` itself
+pub fn thread_counts(max_threads: usize) -> Vec<usize> {
+    let mut counts: Vec<usize> = std::iter::successors(Some(1usize), |n| n.checked_mul(2))
+        .take_while(|n| *n < max_threads)
+        .collect();
+    counts.push(max_threads.max(1));
+    counts
+}
+
+/// Measures the throughput of `f` for every thread count up to `max_threads`
+pub fn measure<T, F: Fn() -> T + Sync>(f: &F, max_threads: usize) -> Vec<ThreadSamples> {
+    thread_counts(max_threads)
+        .into_iter()
+        .map(|threads| {
+            // one untimed run to let the threads and caches settle
+            run_sample(f, threads);
+            ThreadSamples {
+                threads,
+                ops_per_sec: (0..SAMPLE_COUNT).map(|_| run_sample(f, threads)).collect(),
+            }
+        })
+        .collect()
+}
+
+/// Calls `f` once from each of `threads` threads released at the same time by a barrier
+pub fn run_once<T, F: Fn() -> T + Sync>(f: &F, threads: usize) {
+    let barrier = Barrier::new(threads);
+    thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(|| {
+                barrier.wait();
+                black_box(f());
+            });
+        }
+    });
+}
+
+/// Runs `f` on `threads` threads for [`SAMPLE_TIME`] and returns the total number of calls per second
+fn run_sample<T, F: Fn() -> T + Sync>(f: &F, threads: usize) -> f64 {
+    // the main thread is one of the parties so it can start the clock when all workers are ready
+    let barrier = Barrier::new(threads + 1);
+    let stop = AtomicBool::new(false);
+
+    let (ops, elapsed) = thread::scope(|s| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut ops = 0u64;
+                    barrier.wait();
+                    while !stop.load(Ordering::Relaxed) {
+                        for _ in 0..BATCH_SIZE {
+                            black_box(f());
+                        }
+                        ops += BATCH_SIZE;
+                    }
+                    ops
+                })
+            })
+            .collect();
+
+        barrier.wait();
+        let start = Instant::now();
+        thread::sleep(SAMPLE_TIME);
+        stop.store(true, Ordering::Relaxed);
+
+        let ops: u64 = workers.into_iter().map(|w| w.join().unwrap()).sum();
+        (ops, start.elapsed())
+    });
+
+    ops as f64 / elapsed.as_secs_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thread_counts_test() {
+        assert_eq!(thread_counts(0), vec![1]);
+        assert_eq!(thread_counts(1), vec![1]);
+        assert_eq!(thread_counts(4), vec![1, 2, 4]);
+        assert_eq!(thread_counts(6), vec![1, 2, 4, 6]);
+    }
+}
@@ -0,0 +1,426 @@
+//! Machine-readable bench results.
+//!
+//! After `cargo bench` the harness writes every measured bench to a JSON document and an
+//! equivalent CSV file in the results directory, `results/` by default or `--results DIR`.
+//! Both are named after the unix time of the run, e.g. `results/1760781234.json`, so the files
+//! of successive runs can be kept side by side and compared over time. A run that ends in the same
+//! second as an earlier one gets a counter after the time, e.g. `results/1760781234-1.json`.
+//!
+//! Every result is a set of samples in nanoseconds per iteration for a single thread count:
+//! * `iter` and `iter_cold_start` benches have one result with 1 thread
+//! * `iter_race` benches have one result with [`RACE_THREADS`], the samples are the race wall times
+//!   and the time each thread waited for the value in all races is kept in `race_waits`
+//! * `iter_contended` benches have one result per thread count, the samples are
+//!   the time per call as seen by a single thread, i.e. `threads / throughput`
+//!
+//! The benches of the [`workload`](crate::workload) matrix are named `strategy/workload`,
+//! the others measure [`DEFAULT_WORKLOAD`].
+//!
+//! The metadata records where the numbers come from: the toolchain, the CPU and the git commit.
+
+use super::cold_start::RACE_THREADS;
+use super::stats::Summary;
+use super::Bencher;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The directory the results are written to unless `--results DIR` is given, relative to the package root
+pub const DEFAULT_RESULTS_DIR: &str = "results";
+/// The workload of the benches without a `/workload` suffix: `LONG_REGEX` matched against an email
+pub const DEFAULT_WORKLOAD: &str = "email_regex";
+/// The name prefix of the benches measured with [`Bencher::iter_contended`]
+pub const CONTENTION_PREFIX: &str = "contention_";
+/// The name prefix of the benches measured with [`Bencher::iter_race`]
+pub const RACE_PREFIX: &str = "race_";
+/// Name prefixes of the benches that measure something else than the steady-state access,
+/// stripped from the name to get the strategy, e.g. `contention_once_cell_lazy` -> `once_cell_lazy`.
+/// The scenario benches are named after the strategies of the steady-state benches they compare with.
+const SCENARIO_PREFIXES: [&str; 3] = ["cold_start_", CONTENTION_PREFIX, RACE_PREFIX];
+
+/// All results of a `cargo bench` run
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Report {
+    pub metadata: Metadata,
+    pub results: Vec<BenchResult>,
+}
+
+/// Where and when the results were measured
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Metadata {
+    /// Seconds since the unix epoch
+    pub timestamp: u64,
+    /// `rustc --version`
+    pub toolchain: String,
+    pub cpu: String,
+    /// The number of CPUs available to the benches
+    pub cpus: usize,
+    /// `git rev-parse HEAD` with `-dirty` appended if there are uncommitted changes
+    pub git_commit: String,
+}
+
+/// The samples of a single bench for a single thread count
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct BenchResult {
+    /// The name the bench is registered under
+    pub name: String,
+    /// The lazy initialization strategy, the bench name without the scenario prefix and the workload
+    pub strategy: String,
+    /// The [`Workload::NAME`](crate::workload::Workload::NAME) of the value in the lazy static
+    pub workload: String,
+    pub threads: usize,
+    pub summary: Summary,
+    /// Nanoseconds per iteration
+    pub samples: Vec<f64>,
+    /// How long each thread of an `iter_race` bench waited for the value in all races, in nanoseconds.
+    /// Empty for the other benches and left out of their JSON.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub race_waits: Vec<f64>,
+}
+
+impl BenchResult {
+    fn new(name: &str, threads: usize, samples: Vec<f64>) -> Self {
+        let (bench, workload) = name.split_once('/').unwrap_or((name, DEFAULT_WORKLOAD));
+        Self {
+            name: name.to_owned(),
+            strategy: strategy_of(bench).to_owned(),
+            workload: workload.to_owned(),
+            threads,
+            summary: Summary::new(&samples),
+            samples,
+            race_waits: Vec::new(),
+        }
+    }
+
+    /// What the samples measure: `ns wall time` of a whole race for the `race_*` benches, `ns/iter` otherwise
+    pub fn unit(&self) -> &'static str {
+        if scenario_of(&self.name) == RACE_PREFIX {
+            "ns wall time"
+        } else {
+            "ns/iter"
+        }
+    }
+
+    /// Collects the results of a measured bench, none if it was not measured
+    pub fn from_bencher(name: &str, b: &Bencher) -> Vec<Self> {
+        if !b.contention().is_empty() {
+            return b
+                .contention()
+                .iter()
+                .map(|thread_samples| {
+                    let threads = thread_samples.threads;
+                    let ns_per_iter = thread_samples
+                        .ops_per_sec
+                        .iter()
+                        .map(|ops| threads as f64 * 1e9 / ops)
+                        .collect();
+                    Self::new(name, threads, ns_per_iter)
+                })
+                .collect();
+        }
+
+        if b.samples().is_empty() {
+            return Vec::new();
+        }
+        if b.race_waits().is_empty() {
+            return vec![Self::new(name, 1, b.samples().to_vec())];
+        }
+        vec![Self {
+            race_waits: b.race_waits().to_vec(),
+            ..Self::new(name, RACE_THREADS, b.samples().to_vec())
+        }]
+    }
+}
+
+/// The scenario prefix of a bench name, e.g. `cold_start_`, empty for a steady-state bench
+pub fn scenario_of(name: &str) -> &'static str {
+    SCENARIO_PREFIXES
+        .into_iter()
+        .find(|prefix| name.starts_with(prefix))
+        .unwrap_or("")
+}
+
+/// Strips the scenario prefix from a bench name
+fn strategy_of(name: &str) -> &str {
+    &name[scenario_of(name).len()..]
+}
+
+impl Metadata {
+    /// Collects the metadata of the current process and machine.
+    /// Anything that can't be found out is recorded as `unknown`.
+    pub fn collect() -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            toolchain: command_output("rustc", &["--version"]).unwrap_or_else(unknown),
+            cpu: cpu_model().unwrap_or_else(unknown),
+            cpus: thread::available_parallelism().map_or(1, |n| n.get()),
+            git_commit: git_commit().unwrap_or_else(unknown),
+        }
+    }
+}
+
+fn unknown() -> String {
+    "unknown".to_owned()
+}
+
+/// Runs the command and returns its trimmed stdout if it succeeded
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    Some(stdout.trim().to_owned())
+}
+
+fn cpu_model() -> Option<String> {
+    if cfg!(target_os = "macos") {
+        return command_output("sysctl", &["-n", "machdep.cpu.brand_string"]);
+    }
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim() == "model name")
+        .map(|(_, model)| model.trim().to_owned())
+}
+
+fn git_commit() -> Option<String> {
+    let commit = command_output("git", &["rev-parse", "HEAD"])?;
+    let status = command_output("git", &["status", "--porcelain", "--untracked-files=no"])?;
+    Some(if status.is_empty() {
+        commit
+    } else {
+        format!("{commit}-dirty")
+    })
+}
+
+impl Report {
+    /// Writes the report as `<timestamp>.json` and `<timestamp>.csv` into `dir`, creating it if needed.
+    /// If there already are results with the same timestamp they are kept and the files are named
+    /// `<timestamp>-<n>` with the first free `n` instead. Returns the path of the JSON file.
+    pub fn write(&self, dir: &Path) -> io::Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        let timestamp = self.metadata.timestamp;
+        let mut n = 0;
+        loop {
+            let path = dir.join(if n == 0 {
+                format!("{timestamp}.json")
+            } else {
+                format!("{timestamp}-{n}.json")
+            });
+            // create_new, so a concurrent run can't take the same name between a check and the write
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    file.write_all(self.to_json().as_bytes())?;
+                    fs::write(path.with_extension("csv"), self.to_csv())?;
+                    return Ok(path);
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => n += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a report is always serializable")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("invalid bench report: {e}"))
+    }
+
+    /// One row per result with the metadata repeated on every row,
+    /// so the rows of several runs can be concatenated into a single table
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "timestamp,git_commit,toolchain,cpu,cpus,name,strategy,workload,threads,\
+             mean,median,mad,median_ci_lower,median_ci_upper,outliers_low,outliers_high,samples,race_waits\n",
+        );
+        let metadata = &self.metadata;
+        for result in &self.results {
+            let summary = &result.summary;
+            let outliers = &summary.outliers;
+            // space-separated, so the samples of a result stay in a single column
+            let joined = |values: &[f64]| {
+                values
+                    .iter()
+                    .map(f64::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            let row = [
+                metadata.timestamp.to_string(),
+                csv_field(&metadata.git_commit),
+                csv_field(&metadata.toolchain),
+                csv_field(&metadata.cpu),
+                metadata.cpus.to_string(),
+                csv_field(&result.name),
+                csv_field(&result.strategy),
+                csv_field(&result.workload),
+                result.threads.to_string(),
+                summary.mean.to_string(),
+                summary.median.to_string(),
+                summary.mad.to_string(),
+                summary.median_ci.lower.to_string(),
+                summary.median_ci.upper.to_string(),
+                (outliers.low_mild + outliers.low_severe).to_string(),
+                (outliers.high_mild + outliers.high_severe).to_string(),
+                joined(&result.samples),
+                joined(&result.race_waits),
+            ];
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+}
+
+/// Quotes a CSV field if it contains a separator, a quote or a line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Results and reports for the tests of the modules that read them
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::*;
+
+    fn metadata() -> Metadata {
+        Metadata {
+            timestamp: 1_760_000_000,
+            toolchain: "rustc 1.90.0 (1159e78c4 2025-09-14)".to_owned(),
+            cpu: "Intel(R) Xeon(R), 2.20GHz".to_owned(),
+            cpus: 8,
+            git_commit: "48a5400".to_owned(),
+        }
+    }
+
+    pub(crate) fn report(results: Vec<BenchResult>) -> Report {
+        Report {
+            metadata: metadata(),
+            results,
+        }
+    }
+
+    /// The strategy and the workload are taken from `name`, same as for a bench that ran
+    pub(crate) fn result(name: &str, threads: usize, samples: Vec<f64>) -> BenchResult {
+        BenchResult::new(name, threads, samples)
+    }
+
+    /// 50 samples spread around `median`
+    pub(crate) fn samples(median: f64) -> Vec<f64> {
+        (0..50).map(|i| median + (i % 5) as f64 * 0.1).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::contention::ThreadSamples;
+    use crate::harness::Mode;
+
+    fn report() -> Report {
+        fixtures::report(vec![fixtures::result(
+            "lazy_static_local",
+            1,
+            vec![27.0, 28.0, 26.5],
+        )])
+    }
+
+    #[test]
+    fn strategy_test() {
+        assert_eq!(strategy_of("lazy_static_local"), "lazy_static_local");
+        assert_eq!(strategy_of("cold_start_once_cell_lazy"), "once_cell_lazy");
+        assert_eq!(
+            strategy_of("cold_start_vanilla_rust_local"),
+            "vanilla_rust_local"
+        );
+        assert_eq!(strategy_of("contention_std_lazy_lock"), "std_lazy_lock");
+        assert_eq!(strategy_of("race_hand_rolled_lazy"), "hand_rolled_lazy");
+
+        assert_eq!(scenario_of("cold_start_once_cell_lazy"), "cold_start_");
+        assert_eq!(scenario_of("race_hand_rolled_lazy"), RACE_PREFIX);
+        assert_eq!(scenario_of("once_cell_lazy/u64"), "");
+    }
+
+    #[test]
+    fn workload_test() {
+        let result = BenchResult::new("once_cell_lazy/lookup_table", 1, vec![3.0]);
+        assert_eq!(result.strategy, "once_cell_lazy");
+        assert_eq!(result.workload, "lookup_table");
+        assert_eq!(report().results[0].workload, DEFAULT_WORKLOAD);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let report = report();
+        assert_eq!(Report::from_json(&report.to_json()).unwrap(), report);
+        assert!(Report::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn csv_test() {
+        let csv = report().to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with(
+            "1760000000,48a5400,rustc 1.90.0 (1159e78c4 2025-09-14),\"Intel(R) Xeon(R), 2.20GHz\",8,\
+             lazy_static_local,lazy_static_local,email_regex,1,27.166666666666668,27,0.5,"
+        ));
+        assert!(lines[1].ends_with(",27 28 26.5,"));
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn results_of_contended_bench() {
+        let mut b = Bencher::new("contention_hand_rolled_lazy", Mode::Bench, 2);
+        b.contention = vec![
+            ThreadSamples {
+                threads: 1,
+                ops_per_sec: vec![1e8],
+            },
+            ThreadSamples {
+                threads: 2,
+                ops_per_sec: vec![1e8],
+            },
+        ];
+
+        let results = BenchResult::from_bencher("contention_hand_rolled_lazy", &b);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].strategy, "hand_rolled_lazy");
+        assert_eq!((results[0].threads, results[0].samples[0]), (1, 10.0));
+        assert_eq!((results[1].threads, results[1].samples[0]), (2, 20.0));
+
+        let b = Bencher::new("lazy_static_local", Mode::Test, 1);
+        assert!(BenchResult::from_bencher("lazy_static_local", &b).is_empty());
+    }
+
+    #[test]
+    fn results_of_race_bench() {
+        let mut b = Bencher::new("race_once_cell_lazy", Mode::Bench, 2);
+        b.samples = vec![200_000.0, 210_000.0];
+        b.race_waits = vec![1_000.0, 150_000.0, 2_000.0];
+
+        let mut run = report();
+        run.results = BenchResult::from_bencher("race_once_cell_lazy", &b);
+        let race = &run.results[0];
+        assert_eq!(race.threads, RACE_THREADS);
+        assert_eq!(race.race_waits, [1_000.0, 150_000.0, 2_000.0]);
+
+        assert_eq!(Report::from_json(&run.to_json()).unwrap(), run);
+        assert!(run.to_csv().ends_with(",200000 210000,1000 150000 2000\n"));
+        // the other benches have no waits and no field for them
+        assert!(!report().to_json().contains("race_waits"));
+    }
+}
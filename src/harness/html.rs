@@ -0,0 +1,544 @@
+//! A static HTML report of a bench run, a single file with inline SVG charts and no external assets.
+//!
+//! `cargo run -- html [RESULTS_JSON]` renders the given results, or the latest ones in `results/`,
+//! next to the JSON file:
+//! * a violin plot with a box plot on top for every bench, each on its own scale
+//! * a line chart of the throughput per thread count for the `contention_*` benches
+//!
+//! `cargo run -- html --baselines BASE NEW` renders the comparison of two baselines saved with
+//! `--save-baseline`: the change of the median of every bench with its confidence interval and verdict.
+
+use super::baseline;
+use super::export::{BenchResult, Metadata, Report, CONTENTION_PREFIX};
+use super::format_ns;
+use super::stats::{percentile, sorted};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The size of a single distribution plot
+const PLOT_WIDTH: f64 = 480.0;
+const PLOT_HEIGHT: f64 = 56.0;
+/// The size of the thread scaling chart, the legend is on the right of the plot area
+const CHART_WIDTH: f64 = 860.0;
+const CHART_HEIGHT: f64 = 380.0;
+const CHART_PLOT_WIDTH: f64 = 560.0;
+/// The number of points the density of a violin plot is evaluated at
+const DENSITY_POINTS: usize = 64;
+/// The change drawn at the full width of a comparison bar, larger changes are clipped
+const MAX_BAR_CHANGE: f64 = 0.5;
+/// Line colors of the scaling chart, reused if there are more strategies than colors
+const PALETTE: [&str; 10] = [
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7",
+    "#9c755f", "#bab0ac",
+];
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse}td,th{padding:2px 10px;text-align:left;vertical-align:middle}\
+tr:nth-child(even){background:#f6f6f6}td.n{text-align:right;font-variant-numeric:tabular-nums}\
+.meta{color:#555}svg text{font-size:11px;fill:#444}";
+
+/// Renders the report of a single run
+pub fn report(report: &Report) -> String {
+    let mut body = String::new();
+    let _ = write!(
+        body,
+        "<h1>Bench results</h1><p class=\"meta\">{}</p>",
+        escape(&describe(&report.metadata))
+    );
+
+    let (contention, others): (Vec<&BenchResult>, Vec<&BenchResult>) = report
+        .results
+        .iter()
+        .partition(|result| result.name.starts_with(CONTENTION_PREFIX));
+
+    body.push_str("<h2>Distributions</h2>");
+    body.push_str(
+        "<p>Each bench on its own scale, the box spans the quartiles with the median in it, \
+         the whiskers reach the furthest samples within 1.5 IQR.</p>",
+    );
+    body.push_str(
+        "<table><tr><th>Bench</th><th>Threads</th><th>Samples</th><th>Unit</th>\
+         <th>Median</th><th>95% CI</th><th>Outliers</th></tr>",
+    );
+    for result in &others {
+        let summary = &result.summary;
+        let _ = write!(
+            body,
+            "<tr><td>{}</td><td class=\"n\">{}</td><td>{}</td><td>{}</td><td class=\"n\">{}</td>\
+             <td class=\"n\">[{}, {}]</td><td class=\"n\">{}</td></tr>",
+            escape(&result.name),
+            result.threads,
+            distribution_plot(&result.samples),
+            result.unit(),
+            format_ns(summary.median),
+            format_ns(summary.median_ci.lower),
+            format_ns(summary.median_ci.upper),
+            summary.outliers.total()
+        );
+    }
+    body.push_str("</table>");
+
+    if !contention.is_empty() {
+        body.push_str("<h2>Thread scaling</h2>");
+        body.push_str(
+            "<p>The total throughput of the <code>contention_*</code> benches, \
+             it grows with the thread count if the lazy static doesn't suffer from contention.</p>",
+        );
+        body.push_str(&scaling_chart(&contention));
+    }
+
+    page("Bench results", &body)
+}
+
+/// Renders the comparison of the baseline `new_name` against `base_name`
+pub fn comparison(base_name: &str, base: &Report, new_name: &str, new: &Report) -> String {
+    let mut body = String::new();
+    let _ = write!(
+        body,
+        "<h1>{} vs {}</h1>",
+        escape(new_name),
+        escape(base_name)
+    );
+    for (name, report) in [(base_name, base), (new_name, new)] {
+        let _ = write!(
+            body,
+            "<p class=\"meta\">{}: {}</p>",
+            escape(name),
+            escape(&describe(&report.metadata))
+        );
+    }
+
+    body.push_str(
+        "<table><tr><th>Bench</th><th>Threads</th><th>Base</th><th>New</th>\
+         <th>Change [95% CI]</th><th></th><th>Verdict</th></tr>",
+    );
+    for compared in baseline::compare(base, &new.results) {
+        let result = compared.result;
+        let _ = write!(
+            body,
+            "<tr><td>{}</td><td class=\"n\">{}</td>",
+            escape(&result.name),
+            result.threads
+        );
+        let Some(comparison) = compared.comparison else {
+            let _ = write!(
+                body,
+                "<td></td><td class=\"n\">{}</td><td colspan=\"3\">not in {}</td></tr>",
+                format_ns(result.summary.median),
+                escape(base_name)
+            );
+            continue;
+        };
+        let base_median = base
+            .results
+            .iter()
+            .find(|base| base.name == result.name && base.threads == result.threads)
+            .map_or(f64::NAN, |base| base.summary.median);
+        let _ = write!(
+            body,
+            "<td class=\"n\">{}</td><td class=\"n\">{}</td>\
+             <td class=\"n\">{:+.1}% [{:+.1}%, {:+.1}%]</td><td>{}</td><td>{}</td></tr>",
+            format_ns(base_median),
+            format_ns(result.summary.median),
+            comparison.change * 100.0,
+            comparison.change_ci.lower * 100.0,
+            comparison.change_ci.upper * 100.0,
+            change_bar(
+                comparison.change,
+                comparison.change_ci.lower,
+                comparison.change_ci.upper,
+                comparison.is_significant()
+            ),
+            comparison.verdict()
+        );
+    }
+    body.push_str("</table>");
+
+    page(&format!("{new_name} vs {base_name}"), &body)
+}
+
+/// Renders the results in `results_path` into an HTML file next to it, returns the path of the file
+pub fn write_report(results_path: &Path) -> Result<PathBuf, String> {
+    let json = fs::read_to_string(results_path)
+        .map_err(|e| format!("cannot read {}: {e}", results_path.display()))?;
+    let path = results_path.with_extension("html");
+    write(&path, &report(&Report::from_json(&json)?))
+}
+
+/// Renders the comparison of two baselines in `results_dir` into `baselines/BASE-vs-NEW.html`
+pub fn write_comparison(
+    results_dir: &Path,
+    base_name: &str,
+    new_name: &str,
+) -> Result<PathBuf, String> {
+    let base = baseline::load(results_dir, base_name)?;
+    let new = baseline::load(results_dir, new_name)?;
+    let path = baseline::path(results_dir, base_name)
+        .with_file_name(format!("{base_name}-vs-{new_name}.html"));
+    write(&path, &comparison(base_name, &base, new_name, &new))
+}
+
+fn write(path: &Path, html: &str) -> Result<PathBuf, String> {
+    fs::write(path, html).map_err(|e| format!("cannot write {}: {e}", path.display()))?;
+    Ok(path.to_owned())
+}
+
+/// Where the results come from, e.g. `rustc 1.90.0 on Xeon (4 CPUs) at commit abc1234`
+fn describe(metadata: &Metadata) -> String {
+    format!(
+        "{} on {} ({} CPU{}) at commit {}",
+        metadata.toolchain,
+        metadata.cpu,
+        metadata.cpus,
+        if metadata.cpus == 1 { "" } else { "s" },
+        metadata.git_commit
+    )
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\"><head><meta charset=\"utf-8\">\
+         <title>{}</title><style>{STYLE}</style></head><body>{body}</body></html>\n",
+        escape(title)
+    )
+}
+
+/// A violin plot of the samples with a box plot on top, scaled from the smallest to the largest sample
+fn distribution_plot(samples: &[f64]) -> String {
+    let sorted = sorted(samples);
+    let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+    // a flat distribution still gets a visible box
+    let (lo, hi) = if max > min {
+        (min, max)
+    } else {
+        (min - 1.0, max + 1.0)
+    };
+    let pad = 8.0;
+    let x = |value: f64| pad + (value - lo) / (hi - lo) * (PLOT_WIDTH - 2.0 * pad);
+    let center = 22.0;
+
+    let mut svg = format!(
+        "<svg width=\"{PLOT_WIDTH}\" height=\"{PLOT_HEIGHT}\" viewBox=\"0 0 {PLOT_WIDTH} {PLOT_HEIGHT}\">"
+    );
+
+    let density = density(&sorted);
+    let peak = density.iter().map(|(_, d)| *d).fold(0.0, f64::max);
+    if peak > 0.0 {
+        let half_height = 18.0;
+        let upper = density
+            .iter()
+            .map(|(value, d)| (x(*value), center - d / peak * half_height));
+        let lower = density
+            .iter()
+            .rev()
+            .map(|(value, d)| (x(*value), center + d / peak * half_height));
+        let _ = write!(
+            svg,
+            "<polygon points=\"{}\" fill=\"#c6dbef\" stroke=\"#6b9ac4\"/>",
+            points(upper.chain(lower))
+        );
+    }
+
+    let q1 = percentile(&sorted, 0.25);
+    let median = percentile(&sorted, 0.5);
+    let q3 = percentile(&sorted, 0.75);
+    let iqr = q3 - q1;
+    let low_whisker = sorted
+        .iter()
+        .copied()
+        .find(|v| *v >= q1 - 1.5 * iqr)
+        .unwrap_or(min);
+    let high_whisker = sorted
+        .iter()
+        .rev()
+        .copied()
+        .find(|v| *v <= q3 + 1.5 * iqr)
+        .unwrap_or(max);
+
+    let _ = write!(
+        svg,
+        "<line x1=\"{:.1}\" x2=\"{:.1}\" y1=\"{center}\" y2=\"{center}\" stroke=\"#333\"/>\
+         <rect x=\"{:.1}\" y=\"{}\" width=\"{:.1}\" height=\"12\" fill=\"#fff\" fill-opacity=\"0.7\" stroke=\"#333\"/>\
+         <line x1=\"{:.1}\" x2=\"{:.1}\" y1=\"{}\" y2=\"{}\" stroke=\"#d62728\" stroke-width=\"2\"/>",
+        x(low_whisker),
+        x(high_whisker),
+        x(q1),
+        center - 6.0,
+        x(q3) - x(q1),
+        x(median),
+        x(median),
+        center - 6.0,
+        center + 6.0,
+    );
+    for outlier in sorted
+        .iter()
+        .filter(|v| **v < low_whisker || **v > high_whisker)
+    {
+        let _ = write!(
+            svg,
+            "<circle cx=\"{:.1}\" cy=\"{center}\" r=\"2\" fill=\"none\" stroke=\"#333\"/>",
+            x(*outlier)
+        );
+    }
+    let _ = write!(
+        svg,
+        "<text x=\"{pad}\" y=\"52\">{}</text><text x=\"{}\" y=\"52\" text-anchor=\"end\">{}</text></svg>",
+        format_ns(min),
+        PLOT_WIDTH - pad,
+        format_ns(max)
+    );
+    svg
+}
+
+/// A Gaussian kernel density estimate evaluated between the smallest and the largest sample,
+/// with Silverman's rule of thumb for the bandwidth. Empty if the samples don't vary.
+fn density(sorted: &[f64]) -> Vec<(f64, f64)> {
+    let n = sorted.len() as f64;
+    let mean = sorted.iter().sum::<f64>() / n;
+    let std_dev = (sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt();
+    let iqr = percentile(sorted, 0.75) - percentile(sorted, 0.25);
+    // the IQR is zero if most samples are the same, fall back to the standard deviation then
+    let spread = if iqr > 0.0 {
+        std_dev.min(iqr / 1.34)
+    } else {
+        std_dev
+    };
+    let bandwidth = 0.9 * spread * n.powf(-0.2);
+    if bandwidth <= 0.0 || !bandwidth.is_finite() {
+        return Vec::new();
+    }
+
+    let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+    (0..DENSITY_POINTS)
+        .map(|i| {
+            let value = min + (max - min) * i as f64 / (DENSITY_POINTS - 1) as f64;
+            let density = sorted
+                .iter()
+                .map(|sample| (-0.5 * ((value - sample) / bandwidth).powi(2)).exp())
+                .sum::<f64>();
+            (value, density)
+        })
+        .collect()
+}
+
+/// The total throughput in Mops/s per thread count, one line per bench
+fn scaling_chart(results: &[&BenchResult]) -> String {
+    // the first result of each bench with the (threads, Mops/s) of all its results, in the order of the results
+    let mut lines: Vec<(&BenchResult, Vec<(usize, f64)>)> = Vec::new();
+    for &result in results {
+        let throughput = result.threads as f64 * 1e3 / result.summary.median;
+        match lines
+            .iter_mut()
+            .find(|(first, _)| first.name == result.name)
+        {
+            Some((_, points)) => points.push((result.threads, throughput)),
+            None => lines.push((result, vec![(result.threads, throughput)])),
+        }
+    }
+
+    let max_threads = results.iter().map(|r| r.threads).max().unwrap_or(1);
+    let max_log = (max_threads as f64).log2().max(1.0);
+    let max_throughput = lines
+        .iter()
+        .flat_map(|(_, points)| points.iter().map(|(_, t)| *t))
+        .fold(0.0, f64::max)
+        * 1.1;
+    let (left, top, bottom) = (60.0, 20.0, 40.0);
+    let plot_height = CHART_HEIGHT - top - bottom;
+    let x = |threads: usize| left + (threads as f64).log2() / max_log * CHART_PLOT_WIDTH;
+    let y = |throughput: f64| top + plot_height - throughput / max_throughput * plot_height;
+
+    let mut svg = format!(
+        "<svg width=\"{CHART_WIDTH}\" height=\"{CHART_HEIGHT}\" viewBox=\"0 0 {CHART_WIDTH} {CHART_HEIGHT}\">"
+    );
+
+    // horizontal grid lines with the throughput and vertical ones at each power of two
+    for i in 0..=5 {
+        let throughput = max_throughput * i as f64 / 5.0;
+        let _ = write!(
+            svg,
+            "<line x1=\"{left}\" x2=\"{}\" y1=\"{y:.1}\" y2=\"{y:.1}\" stroke=\"#ddd\"/>\
+             <text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{throughput:.1}</text>",
+            left + CHART_PLOT_WIDTH,
+            left - 6.0,
+            y(throughput) + 4.0,
+            y = y(throughput)
+        );
+    }
+    let mut threads = 1;
+    while threads <= max_threads {
+        let _ = write!(
+            svg,
+            "<line x1=\"{x:.1}\" x2=\"{x:.1}\" y1=\"{top}\" y2=\"{}\" stroke=\"#ddd\"/>\
+             <text x=\"{x:.1}\" y=\"{}\" text-anchor=\"middle\">{threads}</text>",
+            top + plot_height,
+            top + plot_height + 16.0,
+            x = x(threads)
+        );
+        threads *= 2;
+    }
+    let _ = write!(
+        svg,
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">threads</text>\
+         <text x=\"14\" y=\"{}\" text-anchor=\"middle\" transform=\"rotate(-90 14 {})\">Mops/s</text>",
+        left + CHART_PLOT_WIDTH / 2.0,
+        CHART_HEIGHT - 4.0,
+        top + plot_height / 2.0,
+        top + plot_height / 2.0
+    );
+
+    for (i, (first, line)) in lines.iter().enumerate() {
+        let color = PALETTE[i % PALETTE.len()];
+        let _ = write!(
+            svg,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{color}\" stroke-width=\"2\"/>",
+            points(line.iter().map(|(threads, t)| (x(*threads), y(*t))))
+        );
+        for (threads, throughput) in line {
+            let _ = write!(
+                svg,
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"{color}\"/>",
+                x(*threads),
+                y(*throughput)
+            );
+        }
+        let legend_x = left + CHART_PLOT_WIDTH + 20.0;
+        let legend_y = top + 10.0 + i as f64 * 18.0;
+        let _ = write!(
+            svg,
+            "<rect x=\"{legend_x}\" y=\"{}\" width=\"12\" height=\"12\" fill=\"{color}\"/>\
+             <text x=\"{}\" y=\"{legend_y}\">{}</text>",
+            legend_y - 10.0,
+            legend_x + 18.0,
+            escape(&first.strategy)
+        );
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// A bar from the zero line to the change with a whisker for its confidence interval,
+/// red if significantly slower, green if significantly faster, grey otherwise
+fn change_bar(change: f64, lower: f64, upper: f64, significant: bool) -> String {
+    let (width, height) = (200.0, 16.0);
+    let x = |change: f64| {
+        width / 2.0 + change.clamp(-MAX_BAR_CHANGE, MAX_BAR_CHANGE) / MAX_BAR_CHANGE * width / 2.0
+    };
+    let color = match (significant, change > 0.0) {
+        (false, _) => "#bbb",
+        (true, true) => "#d62728",
+        (true, false) => "#2ca02c",
+    };
+    let (from, to) = if change > 0.0 {
+        (x(0.0), x(change))
+    } else {
+        (x(change), x(0.0))
+    };
+    format!(
+        "<svg width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\
+         <rect x=\"{from:.1}\" y=\"3\" width=\"{:.1}\" height=\"10\" fill=\"{color}\"/>\
+         <line x1=\"{:.1}\" x2=\"{:.1}\" y1=\"8\" y2=\"8\" stroke=\"#333\"/>\
+         <line x1=\"{:.1}\" x2=\"{:.1}\" y1=\"0\" y2=\"{height}\" stroke=\"#333\"/></svg>",
+        to - from,
+        x(lower),
+        x(upper),
+        x(0.0),
+        x(0.0)
+    )
+}
+
+/// Formats the points of an SVG polygon or polyline
+fn points(points: impl Iterator<Item = (f64, f64)>) -> String {
+    points
+        .map(|(x, y)| format!("{x:.1},{y:.1}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Escapes the characters with a special meaning in HTML text and attributes
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::export::fixtures::{self, result, samples};
+
+    #[test]
+    fn report_test() {
+        let mut run = fixtures::report(vec![
+            result("lazy_static_local", 1, samples(27.0)),
+            result("flat", 1, vec![5.0; 10]),
+            result("contention_once_cell_lazy", 1, samples(30.0)),
+            result("contention_once_cell_lazy", 2, samples(31.0)),
+            result("race_once_cell_lazy", 64, samples(200_000.0)),
+        ]);
+        run.metadata.cpu = "AT&T <cpu>".to_owned();
+        let html = report(&run);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        // self-contained: nothing is loaded from anywhere else
+        assert!(!html.contains("src=") && !html.contains("href=") && !html.contains("<script"));
+        assert!(html.contains("AT&amp;T &lt;cpu&gt;"));
+        // a distribution plot per non-contention bench plus the scaling chart
+        assert_eq!(html.matches("<svg").count(), 4);
+        assert_eq!(
+            html.matches("<polygon").count(),
+            2,
+            "a flat bench has no violin"
+        );
+        assert_eq!(html.matches("<polyline").count(), 1);
+        assert!(html.contains(">once_cell_lazy</text>"));
+        // the same units as in the README
+        assert_eq!(html.matches("<td>ns/iter</td>").count(), 2);
+        assert_eq!(html.matches("<td>ns wall time</td>").count(), 1);
+    }
+
+    #[test]
+    fn comparison_test() {
+        let base = fixtures::report(vec![
+            result("lazy_static_local", 1, samples(27.0)),
+            result("once_cell_lazy", 1, samples(27.0)),
+        ]);
+        let new = fixtures::report(vec![
+            result("lazy_static_local", 1, samples(30.0)),
+            result("once_cell_lazy", 1, samples(27.0)),
+            result("std_lazy_lock", 1, samples(27.0)),
+        ]);
+
+        let html = comparison("main", &base, "feature", &new);
+        assert!(html.contains("<h1>feature vs main</h1>"));
+        assert!(html.contains("significantly slower"));
+        assert!(html.contains("no significant difference"));
+        assert!(html.contains("not in main"));
+        assert!(html.contains("#d62728"));
+    }
+
+    #[test]
+    fn density_test() {
+        assert!(density(&[5.0; 10]).is_empty());
+
+        let points = density(&sorted(&samples(27.0)));
+        assert_eq!(points.len(), DENSITY_POINTS);
+        assert_eq!(points[0].0, 27.0);
+        assert_eq!(points[DENSITY_POINTS - 1].0, 27.4);
+        assert!(points.iter().all(|(_, d)| *d > 0.0));
+    }
+
+    #[test]
+    fn escape_test() {
+        assert_eq!(escape("a < b & \"c\""), "a &lt; b &amp; &quot;c&quot;");
+    }
+}
@@ -0,0 +1,703 @@
+//! A minimal benchmark harness that runs on the stable toolchain.
+//!
+//! libtest's `#[bench]` and `test::Bencher` require `#![feature(test)]`, so the bench target
+//! is declared with `harness = false` and its `main` passes all benches and tests to [`main`].
+//!
+//! * `cargo bench` passes `--bench` to the binary: benches are measured, tests are skipped
+//! * `cargo test` runs the tests and every bench once to check it doesn't panic
+//!
+//! Any other free argument is treated as a name filter, same as with libtest, and `--skip NAME`
+//! leaves out the benches and tests whose names contain `NAME`. The other libtest flags are ignored.
+//!
+//! `--compare A B` prints a verdict on whether benches `A` and `B` differ significantly,
+//! e.g. `cargo bench -- --compare lazy_static_local once_cell_lazy`. It can be repeated.
+//!
+//! `--threads N` sets the maximum number of threads for [`Bencher::iter_contended`],
+//! the number of available CPUs by default.
+//!
+//! `--results DIR` sets the directory the JSON and CSV results of `cargo bench` are written to,
+//! `results` by default. See [`export`].
+//!
+//! `--save-baseline NAME` stores the results as a named baseline, `--baseline NAME` compares them
+//! with it and exits with a non-zero code if any bench regressed by more than `--threshold PERCENT`.
+//! See [`baseline`].
+//!
+//! `--cold-start NAME` is used internally by [`Bencher::iter_cold_start`] to run a single bench
+//! in a child process.
+
+pub mod baseline;
+pub mod cold_start;
+pub mod contention;
+pub mod export;
+pub mod html;
+pub mod readme;
+pub mod stats;
+
+use cold_start::RaceSamples;
+use contention::ThreadSamples;
+use export::{BenchResult, Metadata, Report};
+use stats::{Comparison, Summary};
+use std::hint::black_box;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::AtomicUsize;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long a bench is run for before any samples are taken
+const WARMUP_TIME: Duration = Duration::from_millis(300);
+/// The minimum duration of a single sample. The number of iterations per sample is scaled to match it.
+const SAMPLE_TIME: Duration = Duration::from_millis(2);
+/// The number of samples taken per bench
+const SAMPLE_COUNT: usize = 100;
+
+/// A bench function, an equivalent of `#[bench] fn name(b: &mut test::Bencher)`
+pub type BenchFn = fn(&mut Bencher);
+/// A test function, an equivalent of `#[test] fn name()`
+pub type TestFn = fn();
+
+/// Tells the harness if the benches should be measured or just run once
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    /// `cargo bench`
+    Bench,
+    /// `cargo test`
+    Test,
+    /// A child process started by [`Bencher::iter_cold_start`] to time a single first access
+    ColdStart,
+}
+
+/// Runs the closure passed to [`Bencher::iter`] and collects its timings.
+/// A drop-in replacement for `test::Bencher`.
+pub struct Bencher {
+    /// The name the bench is registered under, needed to select it in a child process
+    name: String,
+    mode: Mode,
+    /// The upper limit of the thread count for [`Bencher::iter_contended`]
+    max_threads: usize,
+    /// Nanoseconds per iteration, one value per sample
+    samples: Vec<f64>,
+    /// Throughput per thread count collected by [`Bencher::iter_contended`]
+    contention: Vec<ThreadSamples>,
+    /// Per-thread wait times collected by [`Bencher::iter_race`]
+    race_waits: Vec<f64>,
+}
+
+impl Bencher {
+    fn new(name: &str, mode: Mode, max_threads: usize) -> Self {
+        Self {
+            name: name.to_owned(),
+            mode,
+            max_threads,
+            samples: Vec::new(),
+            contention: Vec::new(),
+            race_waits: Vec::new(),
+        }
+    }
+
+    /// Runs the closure repeatedly to warm up, then scales up the number of iterations
+    /// so that each sample takes at least [`SAMPLE_TIME`] and records [`SAMPLE_COUNT`] samples.
+    /// The return value of the closure is passed through [`black_box`].
+    pub fn iter<T, F: FnMut() -> T>(&mut self, mut f: F) {
+        if self.mode != Mode::Bench {
+            black_box(f());
+            return;
+        }
+
+        // keep doubling the batch until it takes long enough to be measured reliably
+        // and keep running it until the warmup time is up
+        let warmup_start = Instant::now();
+        let mut iters = 1u64;
+        let mut elapsed = run_batch(&mut f, iters);
+        while warmup_start.elapsed() < WARMUP_TIME {
+            if elapsed < SAMPLE_TIME {
+                iters *= 2;
+            }
+            elapsed = run_batch(&mut f, iters);
+        }
+
+        let ns_per_iter = elapsed.as_nanos() as f64 / iters as f64;
+        let iters = ((SAMPLE_TIME.as_nanos() as f64 / ns_per_iter).ceil() as u64).max(1);
+
+        self.samples = (0..SAMPLE_COUNT)
+            .map(|_| run_batch(&mut f, iters).as_nanos() as f64 / iters as f64)
+            .collect();
+    }
+
+    /// Calls the closure from 1, 2, 4 .. `--threads` threads at the same time and records
+    /// the total throughput for each thread count. See [`contention`] for details.
+    pub fn iter_contended<T, F: Fn() -> T + Sync>(&mut self, f: F) {
+        if self.mode != Mode::Bench {
+            contention::run_once(&f, 2);
+            return;
+        }
+
+        self.contention = contention::measure(&f, self.max_threads);
+    }
+
+    /// Measures a single call to the closure in a fresh process, one process per sample.
+    /// Any lazy static the closure touches is uninitialized on that call, so the samples
+    /// contain the full cost of the first access. See [`cold_start`] for details.
+    ///
+    /// Panics if a child process fails or doesn't report its timing.
+    pub fn iter_cold_start<T, F: FnOnce() -> T>(&mut self, f: F) {
+        let result = match self.mode {
+            Mode::ColdStart => {
+                cold_start::report(f);
+                return;
+            }
+            // check that the child process can be started and reports back
+            Mode::Test => cold_start::run_child(&self.name)
+                .and_then(|report| report.get(cold_start::FIRST_ACCESS).map(|_| ())),
+            Mode::Bench => cold_start::measure(&self.name).map(|samples| self.samples = samples),
+        };
+
+        if let Err(e) = result {
+            panic!("{e}");
+        }
+    }
+
+    /// Releases [`cold_start::RACE_THREADS`] threads calling the closure at the same time
+    /// in a fresh process, one process per sample, so they all race to initialize the same static.
+    /// The samples are the wall times of the races, the per-thread waits are kept separately.
+    ///
+    /// `init_count` must be incremented by the initializer of the static. Panics if it ran
+    /// more than once in any of the races or if a child process fails.
+    pub fn iter_race<T, F: Fn() -> T + Sync>(&mut self, init_count: &AtomicUsize, f: F) {
+        let result = match self.mode {
+            Mode::ColdStart => {
+                cold_start::report_race(f, init_count);
+                return;
+            }
+            // a single race is enough to check that the initializer runs once
+            Mode::Test => cold_start::run_child(&self.name)
+                .and_then(|report| report.get(cold_start::RACE_WAITS).map(|_| ())),
+            Mode::Bench => cold_start::measure_race(&self.name).map(|race| {
+                let RaceSamples { wall, waits } = race;
+                self.samples = wall;
+                self.race_waits = waits;
+            }),
+        };
+
+        if let Err(e) = result {
+            panic!("{e}");
+        }
+    }
+
+    /// Per-iteration timings in nanoseconds, one per sample.
+    /// Empty if `iter` was never called or the harness is in test mode.
+    pub fn samples(&self) -> &[f64] {
+        &self.samples
+    }
+
+    /// Throughput samples per thread count.
+    /// Empty if `iter_contended` was never called or the harness is in test mode.
+    pub fn contention(&self) -> &[ThreadSamples] {
+        &self.contention
+    }
+
+    /// How long each thread waited for the value in all races, in nanoseconds.
+    /// Empty if `iter_race` was never called or the harness is in test mode.
+    pub fn race_waits(&self) -> &[f64] {
+        &self.race_waits
+    }
+}
+
+/// Runs the closure `iters` times and returns the total time it took
+fn run_batch<T, F: FnMut() -> T>(f: &mut F, iters: u64) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iters {
+        black_box(f());
+    }
+    start.elapsed()
+}
+
+/// Command line options passed by `cargo bench` or `cargo test`
+struct Args {
+    mode: Mode,
+    filter: Option<String>,
+    /// Names containing any of these are left out, from `--skip NAME`
+    skip: Vec<String>,
+    /// Pairs of bench names from `--compare A B`
+    compare: Vec<(String, String)>,
+    max_threads: usize,
+    /// The bench to run in a child process from `--cold-start NAME`
+    cold_start: Option<String>,
+    /// Where to write the results to, from `--results DIR`
+    results_dir: PathBuf,
+    /// The baseline to store the results as, from `--save-baseline NAME`
+    save_baseline: Option<String>,
+    /// The baseline to compare the results with, from `--baseline NAME`
+    baseline: Option<String>,
+    /// The relative slowdown that counts as a regression, from `--threshold PERCENT`
+    threshold: f64,
+}
+
+/// The libtest flags followed by a value, which must not be taken for the name filter
+const LIBTEST_VALUE_FLAGS: [&str; 6] = [
+    "--color",
+    "--format",
+    "--logfile",
+    "--shuffle-seed",
+    "--test-threads",
+    "-Z",
+];
+
+impl Args {
+    fn from_env() -> Self {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    fn parse(mut args: impl Iterator<Item = String>) -> Self {
+        let mut mode = Mode::Test;
+        let mut filter = None;
+        let mut skip = Vec::new();
+        let mut compare = Vec::new();
+        let mut max_threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let mut cold_start = None;
+        let mut results_dir = PathBuf::from(export::DEFAULT_RESULTS_DIR);
+        let mut save_baseline = None;
+        let mut baseline = None;
+        let mut threshold = baseline::DEFAULT_THRESHOLD;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--bench" => mode = Mode::Bench,
+                "--compare" => match (args.next(), args.next()) {
+                    (Some(a), Some(b)) => compare.push((a, b)),
+                    _ => {
+                        eprintln!("--compare requires two bench names");
+                        process::exit(2);
+                    }
+                },
+                cold_start::CHILD_FLAG => match args.next() {
+                    Some(name) => cold_start = Some(name),
+                    None => {
+                        eprintln!("{} requires a bench name", cold_start::CHILD_FLAG);
+                        process::exit(2);
+                    }
+                },
+                "--results" => match args.next() {
+                    Some(dir) => results_dir = PathBuf::from(dir),
+                    None => {
+                        eprintln!("--results requires a directory");
+                        process::exit(2);
+                    }
+                },
+                "--save-baseline" => match args.next() {
+                    Some(name) => save_baseline = Some(name),
+                    None => {
+                        eprintln!("--save-baseline requires a baseline name");
+                        process::exit(2);
+                    }
+                },
+                "--baseline" => match args.next() {
+                    Some(name) => baseline = Some(name),
+                    None => {
+                        eprintln!("--baseline requires a baseline name");
+                        process::exit(2);
+                    }
+                },
+                "--threshold" => match args.next().and_then(|n| n.parse::<f64>().ok()) {
+                    Some(percent) if percent >= 0.0 => threshold = percent / 100.0,
+                    _ => {
+                        eprintln!("--threshold requires a non-negative percentage");
+                        process::exit(2);
+                    }
+                },
+                "--threads" => match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) if n > 0 => max_threads = n,
+                    _ => {
+                        eprintln!("--threads requires a positive number");
+                        process::exit(2);
+                    }
+                },
+                "--skip" => match args.next() {
+                    Some(name) => skip.push(name),
+                    None => {
+                        eprintln!("--skip requires a name");
+                        process::exit(2);
+                    }
+                },
+                // other libtest flags, e.g. `--nocapture` or `--format json`, have no meaning here
+                _ if LIBTEST_VALUE_FLAGS.contains(&arg.as_str()) => {
+                    args.next();
+                }
+                _ if arg.starts_with('-') => {}
+                _ => filter = Some(arg),
+            }
+        }
+
+        Self {
+            mode,
+            filter,
+            skip,
+            compare,
+            max_threads,
+            cold_start,
+            results_dir,
+            save_baseline,
+            baseline,
+            threshold,
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|f| name.contains(f.as_str()))
+            && !self.skip.iter().any(|s| name.contains(s.as_str()))
+    }
+}
+
+/// The entry point for a bench target. Runs the benches and tests according to the command line args
+/// and exits with a non-zero code if any of them panicked or regressed against the `--baseline`.
+pub fn main(benches: &[(&str, BenchFn)], tests: &[(&str, TestFn)]) {
+    let args = Args::from_env();
+
+    if let Some(name) = &args.cold_start {
+        match benches.iter().find(|(n, _)| n == name) {
+            Some((_, bench)) => bench(&mut Bencher::new(name, Mode::ColdStart, args.max_threads)),
+            None => {
+                eprintln!("no bench named {name}");
+                process::exit(2);
+            }
+        }
+        return;
+    }
+
+    // loaded before running anything, so a typo in the name doesn't waste a whole bench run
+    let baseline = match (&args.baseline, args.mode) {
+        (Some(name), Mode::Bench) => match baseline::load(&args.results_dir, name) {
+            Ok(report) => Some((name, report)),
+            Err(e) => {
+                eprintln!("{e}");
+                process::exit(2);
+            }
+        },
+        _ => None,
+    };
+
+    let benches: Vec<_> = benches
+        .iter()
+        .filter(|(name, _)| args.matches(name))
+        .collect();
+    let tests: Vec<_> = match args.mode {
+        Mode::Bench | Mode::ColdStart => Vec::new(),
+        Mode::Test => tests
+            .iter()
+            .filter(|(name, _)| args.matches(name))
+            .collect(),
+    };
+
+    let width = benches
+        .iter()
+        .map(|(name, _)| name.len())
+        .chain(tests.iter().map(|(name, _)| name.len()))
+        .max()
+        .unwrap_or_default();
+
+    println!("\nrunning {} tests", benches.len() + tests.len());
+
+    let mut failed = Vec::new();
+    let mut passed = 0;
+    // samples of every measured bench for `--compare`
+    let mut measured = Vec::new();
+    // every measured bench for the JSON and CSV export
+    let mut results = Vec::new();
+
+    for (name, test) in &tests {
+        match panic::catch_unwind(test) {
+            Ok(()) => {
+                passed += 1;
+                println!("test {name:<width$} ... ok");
+            }
+            Err(_) => {
+                failed.push(*name);
+                println!("test {name:<width$} ... FAILED");
+            }
+        }
+    }
+
+    for (name, bench) in &benches {
+        let mut b = Bencher::new(name, args.mode, args.max_threads);
+        match panic::catch_unwind(AssertUnwindSafe(|| bench(&mut b))) {
+            Ok(()) if args.mode == Mode::Bench => {
+                if b.contention.is_empty() {
+                    println!(
+                        "test {name:<width$} ... bench: {}",
+                        format_samples(b.samples())
+                    );
+                } else {
+                    println!("test {name:<width$} ... bench: contention");
+                }
+                if !b.samples.is_empty() {
+                    println!(
+                        "{:width$}          {}",
+                        "",
+                        format_summary(&Summary::new(&b.samples))
+                    );
+                }
+                if !b.race_waits.is_empty() {
+                    println!(
+                        "{:width$}          {}",
+                        "",
+                        format_race_waits(&b.race_waits)
+                    );
+                }
+                for thread_samples in &b.contention {
+                    println!(
+                        "{:width$}          {}",
+                        "",
+                        format_contention(thread_samples)
+                    );
+                }
+                results.extend(BenchResult::from_bencher(name, &b));
+                measured.push((*name, b.samples));
+            }
+            Ok(()) => {
+                passed += 1;
+                println!("test {name:<width$} ... ok");
+            }
+            Err(_) => {
+                failed.push(*name);
+                println!("test {name:<width$} ... FAILED");
+            }
+        }
+    }
+
+    if !args.compare.is_empty() && args.mode == Mode::Bench {
+        println!("\ncomparisons:");
+        for (a, b) in &args.compare {
+            let samples_of = |name: &str| {
+                measured
+                    .iter()
+                    .find(|(n, samples)| *n == name && !samples.is_empty())
+                    .map(|(_, samples)| samples.as_slice())
+            };
+            match (samples_of(a), samples_of(b)) {
+                (Some(base), Some(other)) => {
+                    println!(
+                        "    {b} vs {a}: {}",
+                        format_comparison(&Comparison::new(base, other))
+                    );
+                }
+                _ => println!("    {b} vs {a}: not measured"),
+            }
+        }
+    }
+
+    // benches that got slower than in the baseline by more than the threshold
+    let mut regressions = Vec::new();
+    if let Some((name, baseline)) = &baseline {
+        println!("\ncompared to baseline {name}:");
+        for compared in baseline::compare(baseline, &results) {
+            let label = match compared.result.threads {
+                1 => compared.result.name.clone(),
+                threads => format!("{} ({threads} threads)", compared.result.name),
+            };
+            match &compared.comparison {
+                Some(comparison) if compared.is_regression(args.threshold) => {
+                    println!("    {label}: {}, REGRESSION", format_comparison(comparison));
+                    regressions.push(label);
+                }
+                Some(comparison) => println!("    {label}: {}", format_comparison(comparison)),
+                None => println!("    {label}: not in the baseline"),
+            }
+        }
+    }
+
+    if !results.is_empty() {
+        let report = Report {
+            metadata: Metadata::collect(),
+            results,
+        };
+        match report.write(&args.results_dir) {
+            Ok(path) => println!("\nresults written to {}", path.display()),
+            Err(e) => eprintln!(
+                "\nfailed to write the results to {}: {e}",
+                args.results_dir.display()
+            ),
+        }
+        if let Some(name) = &args.save_baseline {
+            match baseline::save(&report, &args.results_dir, name) {
+                Ok(path) => println!("baseline {name} saved to {}", path.display()),
+                Err(e) => eprintln!("failed to save baseline {name}: {e}"),
+            }
+        }
+    }
+
+    if !failed.is_empty() {
+        println!("\nfailures:");
+        for name in &failed {
+            println!("    {name}");
+        }
+    }
+
+    if !regressions.is_empty() {
+        println!("\nregressions of more than {}%:", args.threshold * 100.0);
+        for label in &regressions {
+            println!("    {label}");
+        }
+    }
+
+    println!(
+        "\ntest result: {}. {passed} passed; {} failed; {} measured\n",
+        if failed.is_empty() { "ok" } else { "FAILED" },
+        failed.len(),
+        measured.len()
+    );
+
+    if !failed.is_empty() {
+        process::exit(101);
+    }
+    if !regressions.is_empty() {
+        process::exit(1);
+    }
+}
+
+/// Formats the samples the same way libtest does: the median and the spread
+/// between the 5th and 95th percentiles, e.g. `27 ns/iter (+/- 1)`
+fn format_samples(samples: &[f64]) -> String {
+    if samples.is_empty() {
+        return "no samples, `Bencher::iter` was not called".to_owned();
+    }
+
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(f64::total_cmp);
+
+    let percentile = |p: f64| sorted[((sorted.len() - 1) as f64 * p).round() as usize];
+    let median = percentile(0.5);
+    let spread = percentile(0.95) - percentile(0.05);
+
+    format!(
+        "{:>11} ns/iter (+/- {})",
+        format_thousands(median.round() as u64),
+        format_thousands(spread.round() as u64)
+    )
+}
+
+/// Formats the summary on a single line, e.g.
+/// `mean 27.1, median 27.0, MAD 0.2, 95% CI [26.9, 27.1] ns, outliers 3 (0 low, 3 high)`
+fn format_summary(summary: &Summary) -> String {
+    let outliers = &summary.outliers;
+    format!(
+        "mean {:.1}, median {:.1}, MAD {:.1}, 95% CI [{:.1}, {:.1}] ns, outliers {} ({} low, {} high)",
+        summary.mean,
+        summary.median,
+        summary.mad,
+        summary.median_ci.lower,
+        summary.median_ci.upper,
+        outliers.total(),
+        outliers.low_mild + outliers.low_severe,
+        outliers.high_mild + outliers.high_severe,
+    )
+}
+
+/// Formats the throughput for a thread count, e.g.
+/// `4 threads:     31.20 Mops/s [30.95, 31.41],     128.2 ns/iter per thread`
+fn format_contention(thread_samples: &ThreadSamples) -> String {
+    let summary = Summary::new(&thread_samples.ops_per_sec);
+    let threads = thread_samples.threads;
+    format!(
+        "{threads:>3} thread{}: {:>10.2} Mops/s [{:.2}, {:.2}], {:>9.1} ns/iter per thread",
+        if threads == 1 { " " } else { "s" },
+        summary.median / 1e6,
+        summary.median_ci.lower / 1e6,
+        summary.median_ci.upper / 1e6,
+        threads as f64 * 1e9 / summary.median
+    )
+}
+
+/// Formats the per-thread wait times of race benches, e.g.
+/// `waits per thread: median 350123.0, MAD 1234.5, max 401234.0 ns over 1280 threads`
+fn format_race_waits(waits: &[f64]) -> String {
+    let summary = Summary::new(waits);
+    format!(
+        "waits per thread: median {:.1}, MAD {:.1}, max {:.1} ns over {} threads",
+        summary.median,
+        summary.mad,
+        waits.iter().copied().fold(f64::MIN, f64::max),
+        waits.len()
+    )
+}
+
+/// Formats the comparison with a verdict, e.g. `+3.1% [+2.5%, +3.8%], significantly slower`
+fn format_comparison(comparison: &Comparison) -> String {
+    format!(
+        "{:+.1}% [{:+.1}%, {:+.1}%], {}",
+        comparison.change * 100.0,
+        comparison.change_ci.lower * 100.0,
+        comparison.change_ci.upper * 100.0,
+        comparison.verdict()
+    )
+}
+
+/// One decimal for short times, thousands separators for the long ones, e.g. `27.3` or `40,608`
+fn format_ns(ns: f64) -> String {
+    if ns < 1000.0 {
+        format!("{ns:.1}")
+    } else {
+        format_thousands(ns.round() as u64)
+    }
+}
+
+/// Adds `,` as a thousands separator, e.g. `40608` -> `40,608`
+fn format_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut output = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            output.push(',');
+        }
+        output.push(c);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_thousands_test() {
+        assert_eq!(format_thousands(0), "0");
+        assert_eq!(format_thousands(27), "27");
+        assert_eq!(format_thousands(9_239), "9,239");
+        assert_eq!(format_thousands(40_608), "40,608");
+        assert_eq!(format_thousands(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn args_test() {
+        let parse = |args: &[&str]| Args::parse(args.iter().map(|&arg| arg.to_owned()));
+
+        let args = parse(&["--bench", "--skip", "race_", "--format", "json", "lazy"]);
+        assert_eq!(args.mode, Mode::Bench);
+        assert_eq!(args.filter.as_deref(), Some("lazy"));
+        assert!(args.matches("lazy_static_local"));
+        assert!(!args.matches("race_lazy_static_local"));
+        assert!(!args.matches("vanilla_rust_local"));
+
+        let args = parse(&["--color", "never", "--test-threads", "1", "--nocapture"]);
+        assert_eq!(args.filter, None);
+        assert!(args.matches("vanilla_rust_local"));
+    }
+
+    #[test]
+    fn bencher_collects_samples() {
+        let mut b = Bencher::new("test", Mode::Bench, 1);
+        b.iter(|| 1 + 1);
+        assert_eq!(b.samples().len(), SAMPLE_COUNT);
+    }
+
+    #[test]
+    fn bencher_runs_once_in_test_mode() {
+        let mut b = Bencher::new("test", Mode::Test, 1);
+        let mut runs = 0;
+        b.iter(|| runs += 1);
+        assert_eq!(runs, 1);
+        assert!(b.samples().is_empty());
+    }
+}
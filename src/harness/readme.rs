@@ -0,0 +1,222 @@
+//! The results table in README.md, generated from the JSON written by [`export`](super::export).
+//!
+//! The table goes between [`START_MARKER`] and [`END_MARKER`], everything else in the README is
+//! left as is. `cargo run -- readme [RESULTS_JSON]` regenerates it from the given file or from
+//! the latest one in `results/`.
+
+use super::export::{scenario_of, BenchResult, Report, DEFAULT_RESULTS_DIR};
+use super::format_ns;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Marks the start of the generated table in README.md
+pub const START_MARKER: &str = "<!-- bench-results:start -->";
+/// Marks the end of the generated table in README.md
+pub const END_MARKER: &str = "<!-- bench-results:end -->";
+/// The bench every other one is compared with: the value built outside of the bench loop without any lazy static.
+/// Each workload and scenario has its own, e.g. `cold_start_vanilla_rust_local` for the `cold_start_*` benches,
+/// so the ratio is the cost of the lazy access on top of the payload.
+pub const REFERENCE_BENCH: &str = "vanilla_rust_local";
+
+/// Whether the bench runs the same loop as [`REFERENCE_BENCH`] with only the lazy access changed.
+/// The `_error` benches never run the payload, the `_reloading` ones share the CPU with the reloading
+/// thread and the `async_` ones add a `block_on` to every iteration, so a ratio would not be the cost
+/// of the lazy access. Compare those with their own counterpart instead, see the bench docs.
+fn same_loop_as_reference(name: &str) -> bool {
+    !(name.ends_with("_error") || name.ends_with("_reloading") || name.starts_with("async_"))
+}
+
+/// Renders the results as a markdown table preceded by a line with the metadata
+pub fn results_table(report: &Report) -> String {
+    let metadata = &report.metadata;
+    // the same scenario, workload and thread count, so both medians measure the same thing
+    let reference = |result: &BenchResult| {
+        if !same_loop_as_reference(&result.name) {
+            return None;
+        }
+        let scenario = scenario_of(&result.name);
+        report
+            .results
+            .iter()
+            .find(|other| {
+                other.strategy == REFERENCE_BENCH
+                    && scenario_of(&other.name) == scenario
+                    && other.workload == result.workload
+                    && other.threads == result.threads
+            })
+            .map(|other| other.summary.median)
+    };
+
+    let mut table = format!(
+        "Measured with `{}` on {} ({} CPU{}) at commit `{}`, \
+         medians with their 95% confidence intervals. \
+         Each bench is compared with the `{REFERENCE_BENCH}` bench of the same scenario, \
+         workload and thread count, `-` if there is none or if the bench runs a different loop, \
+         e.g. the `_error`, `_reloading` and `async_` ones.\n\n",
+        metadata.toolchain,
+        metadata.cpu,
+        metadata.cpus,
+        if metadata.cpus == 1 { "" } else { "s" },
+        metadata.git_commit
+    );
+    table.push_str(&format!(
+        "| Bench | Strategy | Workload | Threads | Median | 95% CI | Unit | vs `{REFERENCE_BENCH}` |\n"
+    ));
+    table.push_str("|---|---|---|--:|--:|--:|---|--:|\n");
+
+    for result in &report.results {
+        let summary = &result.summary;
+        let relative = reference(result).map_or("-".to_owned(), |reference| {
+            format!("{:.2}x", summary.median / reference)
+        });
+        table.push_str(&format!(
+            "| `{}` | {} | {} | {} | {} | [{}, {}] | {} | {relative} |\n",
+            result.name,
+            result.strategy,
+            result.workload,
+            result.threads,
+            format_ns(summary.median),
+            format_ns(summary.median_ci.lower),
+            format_ns(summary.median_ci.upper),
+            result.unit(),
+        ));
+    }
+    table
+}
+
+/// Replaces everything between the markers in `readme` with `table`
+pub fn replace_table(readme: &str, table: &str) -> Result<String, String> {
+    let missing = |marker: &str| format!("no {marker} marker in the README");
+    let start = readme
+        .find(START_MARKER)
+        .ok_or_else(|| missing(START_MARKER))?
+        + START_MARKER.len();
+    let end = readme[start..]
+        .find(END_MARKER)
+        .ok_or_else(|| missing(END_MARKER))?
+        + start;
+
+    Ok(format!("{}\n{table}{}", &readme[..start], &readme[end..]))
+}
+
+/// The most recent results in `results/`
+pub fn latest_results() -> Result<PathBuf, String> {
+    latest_in(Path::new(DEFAULT_RESULTS_DIR))
+}
+
+/// The file names are the unix times of the runs, followed by a counter for the runs
+/// that ended in the same second, see [`Report::write`]
+fn latest_in(dir: &Path) -> Result<PathBuf, String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("cannot read {}: {e}", dir.display()))?;
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            let stem = path.file_stem()?.to_str()?;
+            let (timestamp, n) = stem.split_once('-').unwrap_or((stem, "0"));
+            let order = (timestamp.parse::<u64>().ok()?, n.parse::<u64>().ok()?);
+            Some((order, path))
+        })
+        .max()
+        .map(|(_, path)| path)
+        .ok_or_else(|| format!("no results in {}, run `cargo bench` first", dir.display()))
+}
+
+/// Regenerates the table in the README at `readme_path` from the results in `results_path`
+pub fn update(readme_path: &Path, results_path: &Path) -> Result<(), String> {
+    let read = |path: &Path| {
+        fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))
+    };
+    let report = Report::from_json(&read(results_path)?)?;
+    let readme = replace_table(&read(readme_path)?, &results_table(&report))?;
+    fs::write(readme_path, readme)
+        .map_err(|e| format!("cannot write {}: {e}", readme_path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::harness::export::fixtures::{report, result};
+
+    #[test]
+    fn table_test() {
+        let table = results_table(&report(vec![
+            result("bad_rust_local", 1, vec![40_608.0; 10]),
+            result("vanilla_rust_local", 1, vec![27.0; 10]),
+            result("once_cell_lazy/u64", 1, vec![1.5; 10]),
+            result("vanilla_rust_local/u64", 1, vec![0.5; 10]),
+            result("cold_start_once_cell_lazy", 1, vec![60_000.0; 10]),
+            result("cold_start_vanilla_rust_local", 1, vec![50_000.0; 10]),
+            result("contention_once_cell_lazy", 4, vec![30.0; 10]),
+            result("race_once_cell_lazy", 64, vec![200_000.0; 10]),
+            result("try_lazy_error", 1, vec![0.3; 10]),
+            result("epoch_lazy_reloading", 1, vec![40.0; 10]),
+            result("async_lazy_local", 1, vec![90.0; 10]),
+        ]));
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[0].starts_with(
+            "Measured with `rustc 1.90.0 (1159e78c4 2025-09-14)` on Intel(R) Xeon(R), 2.20GHz (8 CPUs) \
+             at commit `48a5400`"
+        ));
+        assert_eq!(
+            lines[4],
+            "| `bad_rust_local` | bad_rust_local | email_regex | 1 | 40,608 | [40,608, 40,608] | ns/iter | 1504.00x |"
+        );
+        assert_eq!(
+            lines[5],
+            "| `vanilla_rust_local` | vanilla_rust_local | email_regex | 1 | 27.0 | [27.0, 27.0] | ns/iter | 1.00x |"
+        );
+        // compared with the vanilla bench of the same workload
+        assert_eq!(
+            lines[6],
+            "| `once_cell_lazy/u64` | once_cell_lazy | u64 | 1 | 1.5 | [1.5, 1.5] | ns/iter | 3.00x |"
+        );
+        // and of the same scenario
+        assert_eq!(
+            lines[8],
+            "| `cold_start_once_cell_lazy` | once_cell_lazy | email_regex | 1 | 60,000 | [60,000, 60,000] | ns/iter | 1.20x |"
+        );
+        // there is no vanilla bench with contention or racing threads
+        assert!(lines[10].ends_with("| 4 | 30.0 | [30.0, 30.0] | ns/iter | - |"));
+        assert_eq!(
+            lines[11],
+            "| `race_once_cell_lazy` | once_cell_lazy | email_regex | 64 | 200,000 | [200,000, 200,000] | ns wall time | - |"
+        );
+        // nor one running the same loop as the error, reloading and async benches
+        for line in &lines[12..] {
+            assert!(line.ends_with("| ns/iter | - |"), "{line}");
+        }
+        assert_eq!(lines.len(), 15);
+    }
+
+    #[test]
+    fn latest_test() {
+        let dir = std::env::temp_dir().join(format!("readme-test-{}", std::process::id()));
+        let report = report(vec![result("vanilla_rust_local", 1, vec![27.0; 10])]);
+
+        // two runs in the same second both keep their results
+        let first = report.write(&dir).unwrap();
+        let second = report.write(&dir).unwrap();
+        assert_eq!(first.file_name().unwrap(), "1760000000.json");
+        assert_eq!(second.file_name().unwrap(), "1760000000-1.json");
+        assert!(second.with_extension("csv").exists());
+        assert_eq!(latest_in(&dir).unwrap(), second);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn replace_table_test() {
+        let readme = format!("# Title\n{START_MARKER}\nold\n{END_MARKER}\nrest\n");
+        assert_eq!(
+            replace_table(&readme, "new\n").unwrap(),
+            format!("# Title\n{START_MARKER}\nnew\n{END_MARKER}\nrest\n")
+        );
+        // regenerating is idempotent
+        let once = replace_table(&readme, "new\n").unwrap();
+        assert_eq!(replace_table(&once, "new\n").unwrap(), once);
+
+        assert!(replace_table("# Title\n", "new\n").is_err());
+        assert!(replace_table(&format!("{START_MARKER}\n"), "new\n").is_err());
+    }
+}
@@ -0,0 +1,277 @@
+//! Summary statistics for bench samples.
+//!
+//! libtest only reports the median and a spread, which hides the shape of the distribution
+//! and makes a 1 ns difference between two benches impossible to interpret.
+//! This module adds robust estimates (median, MAD), bootstrapped confidence intervals
+//! and Tukey's outlier classification, plus a significance test for a pair of benches.
+
+use serde::{Deserialize, Serialize};
+
+/// The number of resamples used to bootstrap confidence intervals
+const BOOTSTRAP_RESAMPLES: usize = 10_000;
+/// The confidence level for all intervals
+const CONFIDENCE_LEVEL: f64 = 0.95;
+/// A fixed seed keeps the bootstrapped intervals reproducible for the same samples
+const BOOTSTRAP_SEED: u64 = 0x2545_F491_4F6C_DD1D;
+
+/// A range of values the true statistic is expected to fall into with [`CONFIDENCE_LEVEL`]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ConfidenceInterval {
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl ConfidenceInterval {
+    pub fn contains(&self, value: f64) -> bool {
+        self.lower <= value && value <= self.upper
+    }
+}
+
+/// Outlier counts using Tukey's fences: _mild_ outliers are more than 1.5 IQR
+/// away from the 1st or 3rd quartile, _severe_ ones are more than 3 IQR away.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct Outliers {
+    pub low_severe: usize,
+    pub low_mild: usize,
+    pub high_mild: usize,
+    pub high_severe: usize,
+}
+
+impl Outliers {
+    fn new(sorted: &[f64]) -> Self {
+        let q1 = percentile(sorted, 0.25);
+        let q3 = percentile(sorted, 0.75);
+        let iqr = q3 - q1;
+
+        let mut outliers = Self::default();
+        for &x in sorted {
+            if x < q1 - 3.0 * iqr {
+                outliers.low_severe += 1;
+            } else if x < q1 - 1.5 * iqr {
+                outliers.low_mild += 1;
+            } else if x > q3 + 3.0 * iqr {
+                outliers.high_severe += 1;
+            } else if x > q3 + 1.5 * iqr {
+                outliers.high_mild += 1;
+            }
+        }
+        outliers
+    }
+
+    pub fn total(&self) -> usize {
+        self.low_severe + self.low_mild + self.high_mild + self.high_severe
+    }
+}
+
+/// Descriptive statistics of a set of samples, all values in ns/iter
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Summary {
+    pub mean: f64,
+    pub median: f64,
+    /// Median absolute deviation from the median, unscaled
+    pub mad: f64,
+    /// Bootstrapped confidence interval of the median
+    pub median_ci: ConfidenceInterval,
+    pub outliers: Outliers,
+}
+
+impl Summary {
+    /// Panics if `samples` is empty
+    pub fn new(samples: &[f64]) -> Self {
+        assert!(
+            !samples.is_empty(),
+            "cannot summarize an empty set of samples"
+        );
+
+        let sorted = sorted(samples);
+        let median = percentile(&sorted, 0.5);
+        let deviations = sorted
+            .iter()
+            .map(|x| (x - median).abs())
+            .collect::<Vec<_>>();
+
+        Self {
+            mean: mean(&sorted),
+            median,
+            mad: median_of(&deviations),
+            median_ci: bootstrap(&[samples], |resampled| median_of(resampled[0])),
+            outliers: Outliers::new(&sorted),
+        }
+    }
+}
+
+/// The result of comparing the medians of two benches
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Comparison {
+    /// Relative change of the median from the base to the other bench, e.g. `0.05` for 5% slower
+    pub change: f64,
+    /// Bootstrapped confidence interval of the relative change
+    pub change_ci: ConfidenceInterval,
+}
+
+impl Comparison {
+    /// Compares the `other` samples against the `base` ones. Panics if either is empty.
+    pub fn new(base: &[f64], other: &[f64]) -> Self {
+        assert!(
+            !base.is_empty() && !other.is_empty(),
+            "cannot compare empty sets of samples"
+        );
+
+        let relative_change =
+            |base: &[f64], other: &[f64]| median_of(other) / median_of(base) - 1.0;
+
+        Self {
+            change: relative_change(base, other),
+            change_ci: bootstrap(&[base, other], |resampled| {
+                relative_change(resampled[0], resampled[1])
+            }),
+        }
+    }
+
+    /// The difference is significant if the confidence interval of the change does not include zero
+    pub fn is_significant(&self) -> bool {
+        !self.change_ci.contains(0.0)
+    }
+
+    /// Whether the other bench is significantly slower, significantly faster or neither
+    pub fn verdict(&self) -> &'static str {
+        match (self.is_significant(), self.change > 0.0) {
+            (false, _) => "no significant difference",
+            (true, true) => "significantly slower",
+            (true, false) => "significantly faster",
+        }
+    }
+}
+
+/// Resamples every set of samples with replacement [`BOOTSTRAP_RESAMPLES`] times,
+/// applies the statistic to each resampled set and returns the confidence interval of the results.
+fn bootstrap<F: Fn(&[&[f64]]) -> f64>(samples: &[&[f64]], statistic: F) -> ConfidenceInterval {
+    let mut rng = XorShift(BOOTSTRAP_SEED);
+    let mut buffers: Vec<Vec<f64>> = samples
+        .iter()
+        .map(|s| Vec::with_capacity(s.len()))
+        .collect();
+
+    let mut estimates = (0..BOOTSTRAP_RESAMPLES)
+        .map(|_| {
+            for (buffer, samples) in buffers.iter_mut().zip(samples) {
+                buffer.clear();
+                buffer.extend((0..samples.len()).map(|_| samples[rng.below(samples.len())]));
+            }
+            let resampled = buffers.iter().map(Vec::as_slice).collect::<Vec<_>>();
+            statistic(&resampled)
+        })
+        .collect::<Vec<_>>();
+    estimates.sort_by(f64::total_cmp);
+
+    let tail = (1.0 - CONFIDENCE_LEVEL) / 2.0;
+    ConfidenceInterval {
+        lower: percentile(&estimates, tail),
+        upper: percentile(&estimates, 1.0 - tail),
+    }
+}
+
+pub(crate) fn sorted(samples: &[f64]) -> Vec<f64> {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    sorted
+}
+
+fn mean(samples: &[f64]) -> f64 {
+    samples.iter().sum::<f64>() / samples.len() as f64
+}
+
+fn median_of(samples: &[f64]) -> f64 {
+    percentile(&sorted(samples), 0.5)
+}
+
+/// Linear interpolation between the closest ranks, `p` is in `0.0..=1.0`.
+/// `sorted` must be sorted in ascending order and not empty.
+pub(crate) fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = (sorted.len() - 1) as f64 * p;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+/// A tiny PRNG for resampling. Statistical quality of xorshift64 is more than enough for it
+/// and it saves pulling in `rand` as a dependency.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A random number in `0..n`
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_test() {
+        let samples = [5.0, 1.0, 4.0, 2.0, 3.0];
+        let summary = Summary::new(&samples);
+        assert_eq!(summary.mean, 3.0);
+        assert_eq!(summary.median, 3.0);
+        assert_eq!(summary.mad, 1.0);
+        assert!(summary.median_ci.contains(3.0));
+        assert_eq!(summary.outliers.total(), 0);
+    }
+
+    #[test]
+    fn outliers_test() {
+        // Q1 = 4.75, Q3 = 16.25 and IQR = 11.5 with the extra values included
+        let mut samples = (1..=20).map(f64::from).collect::<Vec<_>>();
+        samples.extend([-40.0, -15.0, 35.0, 60.0]);
+
+        let outliers = Summary::new(&samples).outliers;
+        assert_eq!(
+            outliers,
+            Outliers {
+                low_severe: 1,
+                low_mild: 1,
+                high_mild: 1,
+                high_severe: 1
+            }
+        );
+    }
+
+    #[test]
+    fn comparison_test() {
+        let base = (0..100).map(|i| 100.0 + (i % 5) as f64).collect::<Vec<_>>();
+        let slower = base.iter().map(|x| x * 1.2).collect::<Vec<_>>();
+        let same = base.iter().rev().copied().collect::<Vec<_>>();
+
+        let comparison = Comparison::new(&base, &slower);
+        assert!(comparison.is_significant());
+        assert!((comparison.change - 0.2).abs() < 1e-9);
+        assert_eq!(comparison.verdict(), "significantly slower");
+        assert_eq!(
+            Comparison::new(&slower, &base).verdict(),
+            "significantly faster"
+        );
+
+        assert!(!Comparison::new(&base, &same).is_significant());
+        assert_eq!(
+            Comparison::new(&base, &same).verdict(),
+            "no significant difference"
+        );
+    }
+
+    #[test]
+    fn percentile_test() {
+        let sorted = [1.0, 2.0, 3.0, 4.0];
+        assert_eq!(percentile(&sorted, 0.0), 1.0);
+        assert_eq!(percentile(&sorted, 0.5), 2.5);
+        assert_eq!(percentile(&sorted, 1.0), 4.0);
+    }
+}
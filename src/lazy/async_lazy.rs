@@ -0,0 +1,227 @@
+//! A lazy value with an async initializer, e.g. a regex read from a file or a local socket.
+//!
+//! The first task to call `get().await` runs the initializer, the others wait for it without
+//! blocking their thread. It works with any executor, the tests and benches use
+//! [`executor`](crate::executor).
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::task::{Poll, Waker};
+
+/// The type of the initializer future that can be named in a `static`
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// A value initialized by the future returned from `F` on the first access.
+///
+/// If the task running the initializer is cancelled, i.e. its `get()` future is dropped before
+/// it completes, the next waiting task calls `F` again. That's why `F` is `Fn` and not `FnOnce`.
+///
+/// ```
+/// use rust_benchmarks::executor::block_on;
+/// use rust_benchmarks::lazy::AsyncLazy;
+///
+/// static COMPILED_REGEX: AsyncLazy<regex::Regex> =
+///     AsyncLazy::new(|| Box::pin(async { regex::Regex::new("^[a-z]+$").unwrap() }));
+///
+/// block_on(async {
+///     assert!(COMPILED_REGEX.get().await.is_match("abc"));
+/// });
+/// ```
+pub struct AsyncLazy<T, F = fn() -> BoxFuture<T>> {
+    init: F,
+    value: OnceLock<T>,
+    state: Mutex<State>,
+}
+
+struct State {
+    /// Set while a task is running the initializer
+    running: bool,
+    /// The tasks waiting for the running initializer to finish
+    waiters: Vec<Waker>,
+}
+
+/// What a task has to do next, see `AsyncLazy::acquire`
+enum Acquired<'a, T> {
+    Value(&'a T),
+    Initialize,
+}
+
+impl<T, F> AsyncLazy<T, F> {
+    /// Creates a new lazy value that will be initialized by the future returned from `f`
+    /// on the first access
+    pub const fn new(f: F) -> Self {
+        Self {
+            init: f,
+            value: OnceLock::new(),
+            state: Mutex::new(State {
+                running: false,
+                waiters: Vec::new(),
+            }),
+        }
+    }
+
+    /// Returns the value if it has been initialized, without initializing it
+    pub fn get_now(&self) -> Option<&T> {
+        self.value.get()
+    }
+
+    /// Resolves to the value once it is initialized, or to `Initialize` if nobody is running
+    /// the initializer, in which case the caller becomes the one running it
+    async fn acquire(&self) -> Acquired<'_, T> {
+        std::future::poll_fn(|cx| {
+            // the initializer runs outside of the lock, which only guards setting `running` and pushing
+            // a waker. A panic in `Waker::clone` leaves both as they were, so the state is still valid
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+            // checked under the lock, `Running` is dropped after the value is set and takes the lock
+            // to wake the waiters, so a waiter registered here cannot miss the wake-up
+            if let Some(value) = self.value.get() {
+                return Poll::Ready(Acquired::Value(value));
+            }
+            if !state.running {
+                state.running = true;
+                return Poll::Ready(Acquired::Initialize);
+            }
+
+            if !state
+                .waiters
+                .iter()
+                .any(|waker| waker.will_wake(cx.waker()))
+            {
+                state.waiters.push(cx.waker().clone());
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+impl<T, F: Fn() -> Fut, Fut: Future<Output = T>> AsyncLazy<T, F> {
+    /// Initializes the value if needed and resolves to a reference to it.
+    /// Concurrent callers wait for the one running the initializer.
+    pub async fn get(&self) -> &T {
+        if let Some(value) = self.value.get() {
+            return value;
+        }
+
+        loop {
+            match self.acquire().await {
+                Acquired::Value(value) => return value,
+                Acquired::Initialize => {
+                    // wakes the waiters even if this future is dropped in the middle of `init`
+                    let _running = Running(&self.state);
+                    let value = (self.init)().await;
+                    // nobody else runs the initializer while `running` is set, so `set` cannot fail
+                    let _ = self.value.set(value);
+                }
+            }
+        }
+    }
+}
+
+/// Clears `State::running` and wakes the waiting tasks when the initializer completes or is cancelled
+struct Running<'a>(&'a Mutex<State>);
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        let waiters = {
+            let mut state = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+            state.running = false;
+            std::mem::take(&mut state.waiters)
+        };
+        waiters.into_iter().for_each(Waker::wake);
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for AsyncLazy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value.get() {
+            Some(value) => f.debug_tuple("AsyncLazy").field(value).finish(),
+            None => f.write_str("AsyncLazy(<uninit>)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::{block_on, yield_now, LocalExecutor};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    #[test]
+    fn initializes_once() {
+        let count = AtomicUsize::new(0);
+        let lazy = AsyncLazy::new(|| async {
+            yield_now().await;
+            count.fetch_add(1, Ordering::SeqCst) + 42
+        });
+
+        assert_eq!(lazy.get_now(), None);
+        assert_eq!(format!("{lazy:?}"), "AsyncLazy(<uninit>)");
+        assert_eq!(block_on(lazy.get()), &42);
+        assert_eq!(block_on(lazy.get()), &42);
+        assert_eq!(format!("{lazy:?}"), "AsyncLazy(42)");
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn initializes_once_across_tasks() {
+        let count = AtomicUsize::new(0);
+        let lazy = AsyncLazy::new(|| async {
+            // gives the other tasks a chance to find the initializer running
+            for _ in 0..3 {
+                yield_now().await;
+            }
+            count.fetch_add(1, Ordering::SeqCst) + 42
+        });
+
+        let executor = LocalExecutor::new();
+        for _ in 0..8 {
+            executor.spawn(async { assert_eq!(lazy.get().await, &42) });
+        }
+        executor.run();
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn initializes_once_across_threads() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        static LAZY: AsyncLazy<usize> = AsyncLazy::new(|| {
+            Box::pin(async {
+                yield_now().await;
+                COUNT.fetch_add(1, Ordering::SeqCst) + 42
+            })
+        });
+
+        thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| assert_eq!(block_on(LAZY.get()), &42));
+            }
+        });
+        assert_eq!(COUNT.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn retries_after_cancellation() {
+        let count = AtomicUsize::new(0);
+        let lazy = AsyncLazy::new(|| async {
+            count.fetch_add(1, Ordering::SeqCst);
+            yield_now().await;
+            42
+        });
+
+        let executor = LocalExecutor::new();
+        // polled once up to the yield in the initializer, then dropped
+        executor.spawn(async {
+            let mut get = std::pin::pin!(lazy.get());
+            let first_poll = std::future::poll_fn(|cx| Poll::Ready(get.as_mut().poll(cx))).await;
+            assert!(first_poll.is_pending());
+        });
+        executor.spawn(async { assert_eq!(lazy.get().await, &42) });
+        executor.run();
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,111 @@
+//! `declare_lazy!`, a `lazy_static!` look-alike built on top of [`Lazy`](super::Lazy).
+//!
+//! The macro generates the same pattern as the `lazy_static!` expansion in examples/expanded.rs:
+//! a hidden unit-like struct with the name of the static, a `Deref` implementation that keeps
+//! the actual value in a [`Lazy`](super::Lazy) static and a [`LazyStatic`] implementation
+//! for [`initialize`].
+
+/// Implemented by every static declared with [`declare_lazy!`](crate::declare_lazy),
+/// an equivalent of `lazy_static::LazyStatic`
+pub trait LazyStatic {
+    fn initialize(lazy: &Self);
+}
+
+/// Initializes a static declared with [`declare_lazy!`](crate::declare_lazy) if it has not been
+/// initialized yet. An equivalent of `lazy_static::initialize`.
+pub fn initialize<T: LazyStatic>(lazy: &T) {
+    LazyStatic::initialize(lazy);
+}
+
+/// Declares one or more lazily initialized statics, same syntax as `lazy_static!`.
+///
+/// ```
+/// use rust_benchmarks::declare_lazy;
+///
+/// declare_lazy! {
+///     /// Doc comments and other attributes are preserved
+///     pub static ref COMPILED_REGEX: regex::Regex = regex::Regex::new("^[a-z]+$").unwrap();
+///     static ref NUMBERS: Vec<u32> = (1..=3).collect();
+/// }
+///
+/// rust_benchmarks::lazy::initialize(&COMPILED_REGEX);
+/// assert!(COMPILED_REGEX.is_match("abc"));
+/// assert_eq!(NUMBERS.len(), 3);
+/// ```
+#[macro_export]
+macro_rules! declare_lazy {
+    ($(#[$attr:meta])* $vis:vis static ref $N:ident : $T:ty = $e:expr; $($t:tt)*) => {
+        $(#[$attr])*
+        #[allow(missing_copy_implementations)]
+        #[allow(non_camel_case_types)]
+        #[allow(clippy::upper_case_acronyms)]
+        #[allow(dead_code)]
+        $vis struct $N {
+            __private_field: (),
+        }
+
+        #[doc(hidden)]
+        $vis static $N: $N = $N {
+            __private_field: (),
+        };
+
+        impl ::core::ops::Deref for $N {
+            type Target = $T;
+            fn deref(&self) -> &$T {
+                #[inline(always)]
+                fn __static_ref_initialize() -> $T {
+                    $e
+                }
+
+                static LAZY: $crate::lazy::Lazy<$T> = $crate::lazy::Lazy::new(__static_ref_initialize);
+                $crate::lazy::Lazy::force(&LAZY)
+            }
+        }
+
+        impl $crate::lazy::LazyStatic for $N {
+            fn initialize(lazy: &Self) {
+                let _ = &**lazy;
+            }
+        }
+
+        $crate::declare_lazy!($($t)*);
+    };
+    () => ();
+}
+
+// `static` needs a `const` constructor, which `Lazy` doesn't have with the `loom` feature
+#[cfg(all(test, not(feature = "loom")))]
+mod tests {
+    use crate::lazy::initialize;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static INIT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    declare_lazy! {
+        static ref COUNTED: usize = INIT_COUNT.fetch_add(1, Ordering::SeqCst) + 42;
+        /// A doc comment
+        #[allow(unused)]
+        pub(crate) static ref WORDS: Vec<&'static str> = vec!["lazy", "static"];
+    }
+
+    mod inner {
+        declare_lazy! {
+            pub(crate) static ref PUBLIC: u32 = 1;
+        }
+    }
+
+    #[test]
+    fn initialize_test() {
+        initialize(&COUNTED);
+        initialize(&COUNTED);
+        assert_eq!(INIT_COUNT.load(Ordering::SeqCst), 1);
+        assert_eq!(*COUNTED, 42);
+        assert_eq!(INIT_COUNT.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn multiple_declarations_test() {
+        assert_eq!(WORDS.join("_"), "lazy_static");
+        assert_eq!(*inner::PUBLIC, 1);
+    }
+}
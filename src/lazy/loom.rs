@@ -0,0 +1,138 @@
+//! Loom model-checking tests for [`Lazy`], run with
+//! `cargo test --release --features loom --lib lazy::loom`.
+//!
+//! Every test is a loom model that runs its closure once for every possible interleaving
+//! of the threads and memory orderings. Loom reports a data race on `init` or `value` as a
+//! failure, which is what catches an ordering that is too weak or a missing synchronization.
+
+use super::Lazy;
+use loom::sync::atomic::{AtomicUsize, Ordering};
+use loom::sync::Arc;
+use loom::thread;
+use std::panic::{self, AssertUnwindSafe};
+
+/// Two threads force the same `Lazy`, only one of them runs the initializer
+#[test]
+fn concurrent_init() {
+    loom::model(|| {
+        let count = Arc::new(AtomicUsize::new(0));
+        let lazy = Arc::new(Lazy::new({
+            let count = Arc::clone(&count);
+            move || count.fetch_add(1, Ordering::Relaxed) + 42
+        }));
+
+        let other = thread::spawn({
+            let lazy = Arc::clone(&lazy);
+            move || *Lazy::force(&lazy)
+        });
+
+        assert_eq!(*Lazy::force(&lazy), 42);
+        assert_eq!(other.join().unwrap(), 42);
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+    });
+}
+
+/// `get` racing with `force` sees either nothing or the fully initialized value
+#[test]
+fn init_and_read() {
+    loom::model(|| {
+        let lazy = Arc::new(Lazy::new(|| vec![42; 4]));
+
+        let reader = thread::spawn({
+            let lazy = Arc::clone(&lazy);
+            move || Lazy::get(&lazy).cloned()
+        });
+
+        assert_eq!(*Lazy::force(&lazy), vec![42; 4]);
+        if let Some(value) = reader.join().unwrap() {
+            assert_eq!(value, vec![42; 4]);
+        }
+    });
+}
+
+/// The value is dropped exactly once, whichever thread initialized it
+#[test]
+fn init_and_drop() {
+    loom::model(|| {
+        let payload = Arc::new(());
+        let lazy = Arc::new(Lazy::new({
+            let payload = Arc::clone(&payload);
+            move || payload
+        }));
+
+        let other = thread::spawn({
+            let lazy = Arc::clone(&lazy);
+            move || {
+                Lazy::force(&lazy);
+            }
+        });
+        Lazy::force(&lazy);
+        other.join().unwrap();
+
+        assert_eq!(Arc::strong_count(&payload), 2);
+        drop(lazy);
+        assert_eq!(Arc::strong_count(&payload), 1);
+    });
+}
+
+/// Calls `f` and returns whether it panicked
+fn panicked<R>(f: impl FnOnce() -> R) -> bool {
+    panic::catch_unwind(AssertUnwindSafe(f)).is_err()
+}
+
+/// The initializer panics on the first call. The panic poisons the `Lazy`,
+/// so the other thread panics too and the initializer is not called again.
+#[test]
+fn panic_during_init_poisons() {
+    loom::model(|| {
+        let count = Arc::new(AtomicUsize::new(0));
+        let lazy = Arc::new(Lazy::new({
+            let count = Arc::clone(&count);
+            move || match count.fetch_add(1, Ordering::Relaxed) {
+                0 => panic!("first attempt"),
+                n => n,
+            }
+        }));
+
+        let other = thread::spawn({
+            let lazy = Arc::clone(&lazy);
+            move || panicked(|| *Lazy::force(&lazy))
+        });
+
+        assert!(panicked(|| *Lazy::force(&lazy)));
+        assert!(other.join().unwrap());
+        assert_eq!(Lazy::get(&lazy), None);
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+    });
+}
+
+/// The initializer panics on the first call. With `Retry` whichever thread comes second
+/// calls it again and gets the value.
+#[test]
+fn panic_during_init_retries() {
+    loom::model(|| {
+        let count = Arc::new(AtomicUsize::new(0));
+        let lazy = Arc::new(Lazy::with_retry({
+            let count = Arc::clone(&count);
+            move || match count.fetch_add(1, Ordering::Relaxed) {
+                0 => panic!("first attempt"),
+                n => n,
+            }
+        }));
+
+        let other = thread::spawn({
+            let lazy = Arc::clone(&lazy);
+            move || panicked(|| *Lazy::force(&lazy))
+        });
+
+        let this_panicked = panicked(|| *Lazy::force(&lazy));
+        let other_panicked = other.join().unwrap();
+
+        assert!(
+            this_panicked != other_panicked,
+            "exactly one attempt panics"
+        );
+        assert_eq!(Lazy::get(&lazy), Some(&1));
+        assert_eq!(count.load(Ordering::Relaxed), 2);
+    });
+}
@@ -0,0 +1,390 @@
+//! A lazily initialized value, the hand-rolled equivalent of `lazy_static!` and `once_cell::sync::Lazy`.
+//!
+//! It started as `struct Lazy<T: Sync>(Cell<Option<T>>, Once)` in src/main.rs, distilled from the
+//! `lazy_static!` expansion in examples/expanded.rs. That version had two problems:
+//! * `unsafe impl<T: Sync> Sync` allowed a `T: !Send` value to be created on one thread
+//!   and used or dropped on another
+//! * the value was read through `Cell::as_ptr`, which relies on `Cell::set` never being called
+//!   again while references are handed out
+//!
+//! This version keeps the value in `UnsafeCell<MaybeUninit<T>>` guarded by `Once`, same as `std::sync::LazyLock`.
+//!
+//! What happens after the initializer panics is selected by a [`PoisonPolicy`]:
+//! [`Poison`] by default, same as `lazy_static!`, `once_cell` and `LazyLock`, or [`Retry`].
+//!
+//! [`declare_lazy!`](crate::declare_lazy) wraps it into the same syntax as `lazy_static!`.
+//! [`TryLazy`] and [`RetryLazy`] are the variants for fallible initializers.
+//! [`ReloadableLazy`] and [`EpochLazy`] can replace the value after it was initialized.
+//! [`AsyncLazy`] is initialized by a future.
+//!
+//! With the `observe` feature a `Lazy` reports its accesses and initialization to an [`Observer`]
+//! attached with [`Lazy::observed`], e.g. [`InitStats`].
+
+mod async_lazy;
+mod declare;
+#[cfg(all(test, feature = "loom"))]
+mod loom;
+mod observe;
+mod reload;
+mod sync;
+mod try_lazy;
+
+pub use async_lazy::{AsyncLazy, BoxFuture};
+pub use declare::{initialize, LazyStatic};
+pub use observe::{InitReport, InitStats, Observer};
+pub use reload::{EpochGuard, EpochLazy, ReloadGuard, ReloadableLazy};
+pub use try_lazy::{RetryLazy, TryLazy};
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+#[cfg(feature = "observe")]
+use std::{thread, time::Instant};
+
+use self::sync::{const_fn, Once, UnsafeCell};
+
+/// Selects what happens to a [`Lazy`] after its initializer panics
+pub trait PoisonPolicy: private::Sealed {}
+
+/// The panic poisons the [`Lazy`] and every later access panics as well.
+/// The default, same as `lazy_static!`, `once_cell::sync::Lazy` and `std::sync::LazyLock`.
+pub struct Poison;
+
+/// The panic is propagated to the caller, but the next access calls the initializer again.
+/// The initializer must be `FnMut` because it may be called more than once.
+pub struct Retry;
+
+impl PoisonPolicy for Poison {}
+impl PoisonPolicy for Retry {}
+
+mod private {
+    use super::{Lazy, PoisonPolicy};
+
+    pub trait Sealed {}
+    impl Sealed for super::Poison {}
+    impl Sealed for super::Retry {}
+
+    /// Runs the initializer of a [`Lazy`] the way the policy `P` requires.
+    /// `FnOnce` for [`Poison`](super::Poison), `FnMut` for [`Retry`](super::Retry).
+    pub trait Initializer<T, P: PoisonPolicy>: Sized {
+        fn initialize(lazy: &Lazy<T, Self, P>);
+    }
+}
+
+/// A value initialized by `F` on the first access, safe to use in a `static`.
+///
+/// ```
+/// use rust_benchmarks::lazy::Lazy;
+///
+/// static COMPILED_REGEX: Lazy<regex::Regex> = Lazy::new(|| regex::Regex::new("^[a-z]+$").unwrap());
+///
+/// assert!(COMPILED_REGEX.is_match("abc"));
+/// ```
+pub struct Lazy<T, F = fn() -> T, P: PoisonPolicy = Poison> {
+    once: Once,
+    /// Taken out and called by the thread that runs the initialization
+    init: UnsafeCell<Option<F>>,
+    /// Initialized if and only if `once` is completed
+    value: UnsafeCell<MaybeUninit<T>>,
+    /// Set by [`Lazy::observed`], not stored at all without the `observe` feature
+    #[cfg(feature = "observe")]
+    observer: Option<&'static dyn Observer>,
+    _policy: PhantomData<P>,
+}
+
+// SAFETY: sharing `&Lazy` between threads hands out `&T` to all of them, so `T` must be `Sync`.
+// The value is created by whichever thread gets to `Once` first and may be dropped by another one,
+// so `T` must be `Send`. For the same reason `F` must be `Send` as it may be called on any thread.
+// Access to `init` and `value` is synchronized by `once`.
+unsafe impl<T: Send + Sync, F: Send, P: PoisonPolicy> Sync for Lazy<T, F, P> {}
+
+impl<T, F> Lazy<T, F> {
+    const_fn! {
+        /// Creates a new lazy value that will be initialized by `f` on the first access.
+        /// If `f` panics the value is poisoned, see [`Poison`].
+        pub fn new(f: F) -> Self {
+            Self::with_policy(f)
+        }
+    }
+
+    const_fn! {
+        /// Same as [`Lazy::new`], reporting to `observer` like [`Lazy::observed`].
+        ///
+        /// ```
+        /// use rust_benchmarks::lazy::{InitStats, Lazy};
+        ///
+        /// static STATS: InitStats = InitStats::new();
+        /// static NUMBER: Lazy<u32> = Lazy::with_observer(|| 42, &STATS);
+        ///
+        /// assert_eq!(*NUMBER, 42);
+        /// // counted only with `--features observe`
+        /// assert_eq!(STATS.report().derefs, if cfg!(feature = "observe") { 1 } else { 0 });
+        /// ```
+        pub fn with_observer(f: F, observer: &'static dyn Observer) -> Self {
+            Self::with_policy(f).observed(observer)
+        }
+    }
+}
+
+impl<T, F> Lazy<T, F, Retry> {
+    const_fn! {
+        /// Creates a new lazy value that will be initialized by `f` on the first access.
+        /// If `f` panics it is called again on the next access, see [`Retry`].
+        ///
+        /// ```
+        /// use rust_benchmarks::lazy::{Lazy, Retry};
+        ///
+        /// static NUMBER: Lazy<u32, fn() -> u32, Retry> = Lazy::with_retry(|| 42);
+        ///
+        /// assert_eq!(*NUMBER, 42);
+        /// ```
+        pub fn with_retry(f: F) -> Self {
+            Self::with_policy(f)
+        }
+    }
+}
+
+impl<T, F, P: PoisonPolicy> Lazy<T, F, P> {
+    const_fn! {
+        fn with_policy(f: F) -> Self {
+            Self {
+                once: Once::new(),
+                init: UnsafeCell::new(Some(f)),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+                #[cfg(feature = "observe")]
+                observer: None,
+                _policy: PhantomData,
+            }
+        }
+    }
+
+    const_fn! {
+        /// Reports every access to this value and its initialization to `observer`.
+        /// Does nothing without the `observe` feature, so an observed `static` can still be benchmarked.
+        ///
+        /// A closure passed to `new` is not coerced to the `fn() -> T` of a `static` when followed by a method
+        /// call, so a `static` is declared with [`Lazy::with_observer`] instead.
+        pub fn observed(
+            #[cfg_attr(not(feature = "observe"), allow(unused_mut))] mut self,
+            observer: &'static dyn Observer,
+        ) -> Self {
+            #[cfg(feature = "observe")]
+            {
+                self.observer = Some(observer);
+            }
+            #[cfg(not(feature = "observe"))]
+            let _ = observer;
+            self
+        }
+    }
+
+    /// Returns the value if it has been initialized, without initializing it
+    pub fn get(this: &Self) -> Option<&T> {
+        if this.once.is_completed() {
+            // SAFETY: `once` is completed, so `value` was initialized and is never written to again
+            Some(unsafe { this.value_unchecked() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a reference to the value
+    ///
+    /// # Safety
+    /// `once` must be completed
+    unsafe fn value_unchecked(&self) -> &T {
+        self.value.with(|value| (*value).assume_init_ref())
+    }
+
+    /// Calls `init`, timing it for the observer if there is one
+    #[inline(always)]
+    fn run_init(&self, init: impl FnOnce() -> T) -> T {
+        #[cfg(feature = "observe")]
+        if let Some(observer) = self.observer {
+            let start = Instant::now();
+            let value = init();
+            observer.on_init(start.elapsed(), thread::current().id());
+            return value;
+        }
+        init()
+    }
+}
+
+impl<T, F: private::Initializer<T, P>, P: PoisonPolicy> Lazy<T, F, P> {
+    /// Initializes the value if needed and returns a reference to it.
+    /// An equivalent of `lazy_static::initialize`.
+    ///
+    /// If the initializer panics the panic is propagated to the caller. With [`Poison`] every
+    /// subsequent access panics as well, with [`Retry`] the next access calls the initializer again.
+    pub fn force(this: &Self) -> &T {
+        #[cfg(feature = "observe")]
+        if let Some(observer) = this.observer {
+            observer.on_deref();
+        }
+        F::initialize(this);
+
+        // SAFETY: `initialize` returned normally, so `once` is completed and `value` is initialized
+        unsafe { this.value_unchecked() }
+    }
+}
+
+impl<T, F: FnOnce() -> T> private::Initializer<T, Poison> for F {
+    fn initialize(lazy: &Lazy<T, F, Poison>) {
+        lazy.once.call_once(|| {
+            // SAFETY: `call_once` runs this closure on one thread at a time and blocks the others
+            // until it returns, so there are no other references to `init` or `value` at this point
+            let init = lazy.init.with_mut(|init| unsafe { (*init).take() });
+            let init = init.expect("the initializer of Lazy has already been taken");
+            let value = lazy.run_init(init);
+            lazy.value.with_mut(|slot| unsafe { (*slot).write(value) });
+        });
+    }
+}
+
+impl<T, F: FnMut() -> T> private::Initializer<T, Retry> for F {
+    fn initialize(lazy: &Lazy<T, F, Retry>) {
+        // unlike `call_once`, `call_once_force` runs the closure again if a previous run panicked
+        lazy.once.call_once_force(|| {
+            // SAFETY: `call_once_force` runs this closure on one thread at a time and blocks the others
+            // until it returns, so there are no other references to `init` or `value` at this point.
+            // The initializer stays in place until it succeeds, so it can be called again after a panic.
+            let value = lazy.init.with_mut(|init| {
+                let init = unsafe { (*init).as_mut() };
+                lazy.run_init(init.expect("the initializer of Lazy has already been taken"))
+            });
+            lazy.value.with_mut(|slot| unsafe { (*slot).write(value) });
+            // not needed anymore, drop it along with anything it captured
+            lazy.init.with_mut(|init| unsafe { *init = None });
+        });
+    }
+}
+
+impl<T, F: private::Initializer<T, P>, P: PoisonPolicy> Deref for Lazy<T, F, P> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        Lazy::force(self)
+    }
+}
+
+impl<T, F, P: PoisonPolicy> Drop for Lazy<T, F, P> {
+    fn drop(&mut self) {
+        if self.once.is_completed() {
+            // SAFETY: `once` is completed, so `value` was initialized and `&mut self` guarantees no references to it
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+impl<T: fmt::Debug, F, P: PoisonPolicy> fmt::Debug for Lazy<T, F, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match Lazy::get(self) {
+            Some(value) => f.debug_tuple("Lazy").field(value).finish(),
+            None => f.write_str("Lazy(<uninit>)"),
+        }
+    }
+}
+
+// these use `Lazy` outside of a loom model, see `lazy/loom.rs` for the tests with the `loom` feature
+#[cfg(all(test, not(feature = "loom")))]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    #[test]
+    fn initializes_once() {
+        let count = AtomicUsize::new(0);
+        let lazy = Lazy::new(|| count.fetch_add(1, Ordering::SeqCst) + 42);
+
+        assert_eq!(Lazy::get(&lazy), None);
+        assert_eq!(*lazy, 42);
+        assert_eq!(*Lazy::force(&lazy), 42);
+        assert_eq!(Lazy::get(&lazy), Some(&42));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn initializes_once_across_threads() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        static LAZY: Lazy<usize> = Lazy::new(|| COUNT.fetch_add(1, Ordering::SeqCst) + 42);
+
+        let barrier = Barrier::new(8);
+        thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    barrier.wait();
+                    assert_eq!(*LAZY, 42);
+                });
+            }
+        });
+        assert_eq!(COUNT.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn drops_value_only_if_initialized() {
+        let value = Arc::new(());
+
+        let lazy: Lazy<Arc<()>, _> = Lazy::new(|| Arc::clone(&value));
+        drop(lazy);
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        let lazy = Lazy::new(|| Arc::clone(&value));
+        Lazy::force(&lazy);
+        assert_eq!(Arc::strong_count(&value), 2);
+        drop(lazy);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn poison_policy_panics_forever() {
+        let attempts = AtomicUsize::new(0);
+        let lazy = Lazy::new(|| match attempts.fetch_add(1, Ordering::SeqCst) {
+            0 => panic!("first attempt"),
+            n => n,
+        });
+
+        for _ in 0..2 {
+            assert!(panic::catch_unwind(AssertUnwindSafe(|| *lazy)).is_err());
+        }
+        assert_eq!(Lazy::get(&lazy), None);
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn retry_policy_retries_after_panic() {
+        let attempts = AtomicUsize::new(0);
+        let lazy = Lazy::with_retry(|| match attempts.fetch_add(1, Ordering::SeqCst) {
+            0 => panic!("first attempt"),
+            n => n,
+        });
+
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| *lazy)).is_err());
+        assert_eq!(Lazy::get(&lazy), None);
+        assert_eq!(*lazy, 1);
+        assert_eq!(*lazy, 1);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn retry_policy_drops_initializer_on_success() {
+        let captured = Arc::new(());
+        let lazy = Lazy::with_retry({
+            let captured = Arc::clone(&captured);
+            move || Arc::strong_count(&captured)
+        });
+
+        assert_eq!(*lazy, 2);
+        assert_eq!(Arc::strong_count(&captured), 1);
+    }
+
+    #[test]
+    fn debug_test() {
+        let lazy = Lazy::new(|| 42);
+        assert_eq!(format!("{lazy:?}"), "Lazy(<uninit>)");
+        Lazy::force(&lazy);
+        assert_eq!(format!("{lazy:?}"), "Lazy(42)");
+    }
+}
@@ -0,0 +1,158 @@
+//! Instrumentation of [`Lazy`](super::Lazy): how often it is accessed and how its initialization went.
+//!
+//! An [`Observer`] is attached with [`Lazy::with_observer`](super::Lazy::with_observer) or
+//! [`Lazy::observed`](super::Lazy::observed) and is only notified
+//! with the `observe` feature. Without it the observer is not stored and the hooks compile to nothing,
+//! so the same `static` can be benchmarked as is.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::thread::ThreadId;
+use std::time::Duration;
+
+/// Notified of the accesses to a [`Lazy`](super::Lazy) and of its initialization.
+/// Called from any thread that touches the value, hence `Sync`.
+pub trait Observer: Sync {
+    /// Called on every [`Lazy::force`](super::Lazy::force) and deref, including the first one
+    fn on_deref(&self) {}
+
+    /// Called after the initializer returned, with how long it took and the thread it ran on.
+    /// With [`Retry`](super::Retry) the attempts that panicked are not reported.
+    fn on_init(&self, _duration: Duration, _thread: ThreadId) {}
+}
+
+/// An [`Observer`] counting the derefs and initializations, usable in a `static`,
+/// see [`Lazy::with_observer`](super::Lazy::with_observer).
+pub struct InitStats {
+    derefs: AtomicUsize,
+    inits: AtomicUsize,
+    init_nanos: AtomicU64,
+    init_thread: Mutex<Option<ThreadId>>,
+}
+
+/// A snapshot of [`InitStats`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InitReport {
+    pub derefs: usize,
+    pub inits: usize,
+    /// The time spent in the initializer, summed over all initializations
+    pub init_duration: Duration,
+    /// The thread that ran the last initialization
+    pub init_thread: Option<ThreadId>,
+}
+
+impl InitStats {
+    pub const fn new() -> Self {
+        Self {
+            derefs: AtomicUsize::new(0),
+            inits: AtomicUsize::new(0),
+            init_nanos: AtomicU64::new(0),
+            init_thread: Mutex::new(None),
+        }
+    }
+
+    /// Returns what has been recorded so far
+    pub fn report(&self) -> InitReport {
+        InitReport {
+            derefs: self.derefs.load(Ordering::Relaxed),
+            inits: self.inits.load(Ordering::Relaxed),
+            init_duration: Duration::from_nanos(self.init_nanos.load(Ordering::Relaxed)),
+            init_thread: *self
+                .init_thread
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+        }
+    }
+}
+
+impl Default for InitStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Observer for InitStats {
+    fn on_deref(&self) {
+        self.derefs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_init(&self, duration: Duration, thread: ThreadId) {
+        self.inits.fetch_add(1, Ordering::Relaxed);
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.init_nanos.fetch_add(nanos, Ordering::Relaxed);
+        *self
+            .init_thread
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(thread);
+    }
+}
+
+#[cfg(all(test, not(feature = "loom")))]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn stats_record_calls() {
+        let stats = InitStats::new();
+        assert_eq!(
+            stats.report(),
+            InitReport {
+                derefs: 0,
+                inits: 0,
+                init_duration: Duration::ZERO,
+                init_thread: None,
+            }
+        );
+
+        stats.on_deref();
+        stats.on_deref();
+        stats.on_init(Duration::from_millis(2), thread::current().id());
+        stats.on_init(Duration::from_millis(3), thread::current().id());
+
+        assert_eq!(
+            stats.report(),
+            InitReport {
+                derefs: 2,
+                inits: 2,
+                init_duration: Duration::from_millis(5),
+                init_thread: Some(thread::current().id()),
+            }
+        );
+    }
+
+    #[cfg(feature = "observe")]
+    #[test]
+    fn lazy_notifies_observer() {
+        use crate::lazy::Lazy;
+        use std::panic::{self, AssertUnwindSafe};
+
+        static STATS: InitStats = InitStats::new();
+        static LAZY: Lazy<u32> = Lazy::with_observer(|| 42, &STATS);
+
+        let handle = thread::spawn(|| *LAZY);
+        let initializer = handle.thread().id();
+        assert_eq!(handle.join().unwrap(), 42);
+        assert_eq!(*LAZY + *LAZY, 84);
+
+        let report = STATS.report();
+        assert_eq!(report.derefs, 3);
+        assert_eq!(report.inits, 1);
+        assert_eq!(report.init_thread, Some(initializer));
+
+        // a panicked attempt is seen as a deref, but not as an initialization
+        static RETRY_STATS: InitStats = InitStats::new();
+        let mut attempts = 0;
+        let lazy = Lazy::with_retry(|| {
+            attempts += 1;
+            assert!(attempts > 1, "first attempt");
+            attempts
+        })
+        .observed(&RETRY_STATS);
+
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| *lazy)).is_err());
+        assert_eq!(*lazy, 2);
+        let report = RETRY_STATS.report();
+        assert_eq!((report.derefs, report.inits), (2, 1));
+    }
+}
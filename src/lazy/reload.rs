@@ -0,0 +1,462 @@
+//! Lazy values that can be replaced after initialization, e.g. a regex compiled from a config
+//! file that is reloaded at run time.
+//!
+//! Readers get a guard instead of a plain reference because the value may be replaced
+//! while they are using it. There are two ways to keep it alive until the guard is dropped:
+//! * [`ReloadableLazy`] keeps the value in a `RwLock`, a reload waits for the readers
+//!   to release the read lock and the readers wait for the reload to finish
+//! * [`EpochLazy`] keeps the value behind an atomic pointer, similar to `arc_swap::ArcSwap`.
+//!   A reload swaps the pointer without blocking the readers and waits for the readers
+//!   of the old value before dropping it
+
+#[cfg(debug_assertions)]
+use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock, PoisonError, RwLock, RwLockReadGuard};
+use std::thread;
+
+/// A value initialized by `F` on the first access and replaced by [`reload`](Self::reload),
+/// backed by a `RwLock`.
+///
+/// ```
+/// use rust_benchmarks::lazy::ReloadableLazy;
+///
+/// static COMPILED_REGEX: ReloadableLazy<regex::Regex> =
+///     ReloadableLazy::new(|| regex::Regex::new("^[a-z]+$").unwrap());
+///
+/// assert!(COMPILED_REGEX.get().is_match("abc"));
+/// COMPILED_REGEX.reload(regex::Regex::new("^[0-9]+$").unwrap());
+/// assert!(COMPILED_REGEX.get().is_match("123"));
+/// ```
+pub struct ReloadableLazy<T, F = fn() -> T> {
+    init: F,
+    value: OnceLock<RwLock<T>>,
+}
+
+impl<T, F> ReloadableLazy<T, F> {
+    /// Creates a new lazy value that will be initialized by `f` on the first access
+    pub const fn new(f: F) -> Self {
+        Self {
+            init: f,
+            value: OnceLock::new(),
+        }
+    }
+
+    /// Replaces the value, waiting for all the outstanding guards to be dropped.
+    /// The initializer is not called if the value has not been initialized yet.
+    ///
+    /// A thread must not call it while it holds a guard from [`get`](Self::get) of the same
+    /// instance: the write lock would wait for that guard forever. Debug builds panic instead.
+    pub fn reload(&self, value: T) {
+        #[cfg(debug_assertions)]
+        assert!(
+            !holds_guard(self.address()),
+            "ReloadableLazy::reload called while holding a guard of it, it would deadlock"
+        );
+
+        let mut value = Some(value);
+        let lock = self
+            .value
+            .get_or_init(|| RwLock::new(value.take().unwrap()));
+        if let Some(value) = value {
+            // only this assignment can poison the lock, if dropping the previous value panics.
+            // The new value is stored even then, so the lock never holds a half-replaced value
+            *lock.write().unwrap_or_else(PoisonError::into_inner) = value;
+        }
+    }
+
+    /// Identifies the instance in [`HELD_GUARDS`]
+    #[cfg(debug_assertions)]
+    fn address(&self) -> usize {
+        ptr::from_ref(self).addr()
+    }
+}
+
+impl<T, F: Fn() -> T> ReloadableLazy<T, F> {
+    /// Initializes the value if needed and returns a guard holding a read lock on it.
+    /// [`reload`](Self::reload) blocks until the guard is dropped.
+    pub fn get(&self) -> ReloadGuard<'_, T> {
+        let lock = self.value.get_or_init(|| RwLock::new((self.init)()));
+        let guard = lock.read().unwrap_or_else(PoisonError::into_inner);
+        #[cfg(debug_assertions)]
+        let _ = HELD_GUARDS.try_with(|held| held.borrow_mut().push(self.address()));
+        ReloadGuard {
+            guard,
+            #[cfg(debug_assertions)]
+            instance: self.address(),
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+thread_local! {
+    /// The addresses of the `ReloadableLazy` instances the current thread holds guards of,
+    /// once per guard. The guards are not `Send`, so they are dropped by the same thread.
+    static HELD_GUARDS: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Whether the current thread holds a guard of the `ReloadableLazy` at `address`
+#[cfg(debug_assertions)]
+fn holds_guard(address: usize) -> bool {
+    HELD_GUARDS
+        .try_with(|held| held.borrow().contains(&address))
+        .unwrap_or(false)
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for ReloadableLazy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value.get() {
+            Some(lock) => {
+                let value = lock.read().unwrap_or_else(PoisonError::into_inner);
+                f.debug_tuple("ReloadableLazy").field(&*value).finish()
+            }
+            None => f.write_str("ReloadableLazy(<uninit>)"),
+        }
+    }
+}
+
+/// A read lock on the value of a [`ReloadableLazy`]
+pub struct ReloadGuard<'a, T> {
+    guard: RwLockReadGuard<'a, T>,
+    /// The address of the `ReloadableLazy`, removed from `HELD_GUARDS` on drop
+    #[cfg(debug_assertions)]
+    instance: usize,
+}
+
+impl<T> Deref for ReloadGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+#[cfg(debug_assertions)]
+impl<T> Drop for ReloadGuard<'_, T> {
+    fn drop(&mut self) {
+        let _ = HELD_GUARDS.try_with(|held| {
+            let mut held = held.borrow_mut();
+            if let Some(i) = held.iter().position(|&address| address == self.instance) {
+                held.swap_remove(i);
+            }
+        });
+    }
+}
+
+/// A value initialized by `F` on the first access and replaced by [`reload`](Self::reload),
+/// backed by an atomic pointer.
+///
+/// Every reader registers itself in one of two counters, selected by the current epoch.
+/// A reload swaps the pointer, switches to the next epoch, so the new readers use the other
+/// counter, and waits for the counter of the previous epoch to drop to zero. The readers are
+/// never blocked, only the reloads are serialized.
+///
+/// ```
+/// use rust_benchmarks::lazy::EpochLazy;
+///
+/// static COMPILED_REGEX: EpochLazy<regex::Regex> =
+///     EpochLazy::new(|| regex::Regex::new("^[a-z]+$").unwrap());
+///
+/// assert!(COMPILED_REGEX.get().is_match("abc"));
+/// COMPILED_REGEX.reload(regex::Regex::new("^[0-9]+$").unwrap());
+/// assert!(COMPILED_REGEX.get().is_match("123"));
+/// ```
+pub struct EpochLazy<T, F = fn() -> T> {
+    init: F,
+    /// A `Box<T>` leaked by `initialize` or `reload`, null before the first access
+    current: AtomicPtr<T>,
+    /// Incremented by every reload, the lowest bit selects the counter in `readers`
+    epoch: AtomicUsize,
+    /// The number of live guards registered in each epoch
+    readers: [AtomicUsize; 2],
+    /// Held by the thread running `init` or `reload`
+    writer: Mutex<()>,
+    /// `current` owns a `T`, see the `Send` and `Sync` impls below
+    _value: PhantomData<*mut T>,
+}
+
+// SAFETY: the value is owned through `current` and is dropped by whichever thread drops
+// `EpochLazy` or reloads it, so `T` must be `Send`.
+unsafe impl<T: Send, F: Send> Send for EpochLazy<T, F> {}
+
+// SAFETY: `&T` is handed out to all threads, so `T` must be `Sync`, and the value may be
+// created on one thread and dropped on another, so `T` must be `Send`.
+// `F` is only ever called through `&F`, possibly from several threads, so it must be `Sync`.
+unsafe impl<T: Send + Sync, F: Sync> Sync for EpochLazy<T, F> {}
+
+impl<T, F> EpochLazy<T, F> {
+    /// Creates a new lazy value that will be initialized by `f` on the first access
+    pub const fn new(f: F) -> Self {
+        Self {
+            init: f,
+            current: AtomicPtr::new(ptr::null_mut()),
+            epoch: AtomicUsize::new(0),
+            readers: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            writer: Mutex::new(()),
+            _value: PhantomData,
+        }
+    }
+
+    /// Replaces the value without blocking the readers. Returns once all the guards
+    /// holding the previous value have been dropped and the previous value with them.
+    /// The initializer is not called if the value has not been initialized yet.
+    ///
+    /// A thread must not call it while it holds a guard from [`get`](Self::get) of the same
+    /// instance: it would wait for that guard to be dropped forever. Unlike `ReloadableLazy`
+    /// this is not checked in debug builds, a guard may be sent to and dropped by another thread.
+    pub fn reload(&self, value: T) {
+        let new = Box::into_raw(Box::new(value));
+
+        // `writer` guards no data, it only serializes `initialize` and `reload`. A panic in the
+        // initializer or in the drop of a replaced value leaves `current` null or pointing to a whole value
+        let _guard = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let old = self.current.swap(new, Ordering::SeqCst);
+        if old.is_null() {
+            return;
+        }
+
+        // the readers that registered in the previous epoch may still be using `old`,
+        // the ones registering from now on will see `new`
+        let epoch = self.epoch.fetch_add(1, Ordering::SeqCst);
+        let readers = &self.readers[epoch & 1];
+        while readers.load(Ordering::SeqCst) != 0 {
+            thread::yield_now();
+        }
+
+        // SAFETY: `old` came from `Box::into_raw` and it is no longer reachable through `current`.
+        // All the readers that could have loaded it were registered in the previous epoch
+        // and have dropped their guards.
+        drop(unsafe { Box::from_raw(old) });
+    }
+
+    /// Registers a reader in the current epoch and returns the value it may use
+    /// until the returned guard is dropped, or `None` if the value is not initialized.
+    fn read(&self) -> Option<EpochGuard<'_, T>> {
+        let readers = loop {
+            let epoch = self.epoch.load(Ordering::SeqCst);
+            let readers = &self.readers[epoch & 1];
+            readers.fetch_add(1, Ordering::SeqCst);
+
+            // a reload may have switched the epoch after the load above and may already be waiting
+            // for the counter of the previous epoch, so this reader must not use that counter
+            if self.epoch.load(Ordering::SeqCst) == epoch {
+                break readers;
+            }
+            readers.fetch_sub(1, Ordering::SeqCst);
+        };
+
+        let value = self.current.load(Ordering::SeqCst);
+        if value.is_null() {
+            readers.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+
+        // SAFETY: `value` was loaded after registering in the current epoch, so a reload
+        // replacing it waits for this reader to drop the guard before dropping the value
+        let value = unsafe { &*value };
+        Some(EpochGuard { value, readers })
+    }
+}
+
+impl<T, F: Fn() -> T> EpochLazy<T, F> {
+    /// Initializes the value if needed and returns a guard that keeps it alive.
+    /// [`reload`](Self::reload) blocks until the guard is dropped, other readers are not affected.
+    pub fn get(&self) -> EpochGuard<'_, T> {
+        if let Some(guard) = self.read() {
+            return guard;
+        }
+
+        self.initialize();
+        self.read().expect("EpochLazy is initialized")
+    }
+
+    #[cold]
+    fn initialize(&self) {
+        let _guard = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // another thread may have initialized it while this one was waiting for the lock
+        if self.current.load(Ordering::SeqCst).is_null() {
+            let value = Box::into_raw(Box::new((self.init)()));
+            self.current.store(value, Ordering::SeqCst);
+        }
+    }
+}
+
+impl<T, F> Drop for EpochLazy<T, F> {
+    fn drop(&mut self) {
+        let value = *self.current.get_mut();
+        if !value.is_null() {
+            // SAFETY: `value` came from `Box::into_raw` and `&mut self` guarantees there are no guards
+            drop(unsafe { Box::from_raw(value) });
+        }
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for EpochLazy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.read() {
+            Some(value) => f.debug_tuple("EpochLazy").field(&*value).finish(),
+            None => f.write_str("EpochLazy(<uninit>)"),
+        }
+    }
+}
+
+/// Keeps the value of an [`EpochLazy`] alive, see [`EpochLazy::get`]
+pub struct EpochGuard<'a, T> {
+    value: &'a T,
+    /// The counter this reader is registered in
+    readers: &'a AtomicUsize,
+}
+
+impl<T> Deref for EpochGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> Drop for EpochGuard<'_, T> {
+    fn drop(&mut self) {
+        self.readers.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    #[test]
+    fn reloadable_lazy_reload() {
+        let count = AtomicUsize::new(0);
+        let lazy = ReloadableLazy::new(|| count.fetch_add(1, Ordering::SeqCst) + 42);
+
+        assert_eq!(format!("{lazy:?}"), "ReloadableLazy(<uninit>)");
+        assert_eq!(*lazy.get(), 42);
+        lazy.reload(7);
+        assert_eq!(*lazy.get(), 7);
+        assert_eq!(format!("{lazy:?}"), "ReloadableLazy(7)");
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn reloadable_lazy_reload_before_init() {
+        let lazy = ReloadableLazy::new(|| unreachable!("replaced before the first access"));
+        lazy.reload(7);
+        assert_eq!(*lazy.get(), 7);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "it would deadlock")]
+    fn reloadable_lazy_reload_while_reading() {
+        let lazy = ReloadableLazy::new(|| 42);
+        let other = ReloadableLazy::new(|| 0);
+
+        // a guard of another instance doesn't matter
+        let _other = other.get();
+        lazy.reload(7);
+        drop(lazy.get());
+        lazy.reload(8);
+
+        let guard = lazy.get();
+        lazy.reload(*guard + 1);
+    }
+
+    #[test]
+    fn epoch_lazy_reload() {
+        let count = AtomicUsize::new(0);
+        let lazy = EpochLazy::new(|| count.fetch_add(1, Ordering::SeqCst) + 42);
+
+        assert_eq!(format!("{lazy:?}"), "EpochLazy(<uninit>)");
+        assert_eq!(*lazy.get(), 42);
+        lazy.reload(7);
+        assert_eq!(*lazy.get(), 7);
+        assert_eq!(format!("{lazy:?}"), "EpochLazy(7)");
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn epoch_lazy_reload_before_init() {
+        let lazy = EpochLazy::new(|| unreachable!("replaced before the first access"));
+        lazy.reload(7);
+        assert_eq!(*lazy.get(), 7);
+    }
+
+    #[test]
+    fn epoch_lazy_drops_every_value_once() {
+        let value = Arc::new(());
+        let lazy = EpochLazy::new(|| Arc::clone(&value));
+
+        lazy.get();
+        assert_eq!(Arc::strong_count(&value), 2);
+        lazy.reload(Arc::clone(&value));
+        assert_eq!(Arc::strong_count(&value), 2);
+        drop(lazy);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn epoch_lazy_reload_waits_for_readers() {
+        let lazy = EpochLazy::new(|| vec![0; 64]);
+        let guard = lazy.get();
+        let reloaded = AtomicBool::new(false);
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                lazy.reload(vec![1; 64]);
+                reloaded.store(true, Ordering::SeqCst);
+            });
+
+            // new readers are not blocked by the pending reload and get the new value
+            while lazy.get()[0] != 1 {
+                thread::yield_now();
+            }
+            assert!(!reloaded.load(Ordering::SeqCst));
+            assert_eq!(*guard, vec![0; 64]);
+            drop(guard);
+        });
+        assert!(reloaded.load(Ordering::SeqCst));
+    }
+
+    /// Readers check that they never see a half-replaced or freed value while the writer reloads
+    fn reload_under_contention<G: Deref<Target = Vec<usize>>>(
+        get: impl Fn() -> G + Sync,
+        reload: impl Fn(Vec<usize>) + Sync,
+    ) {
+        let done = AtomicBool::new(false);
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    while !done.load(Ordering::SeqCst) {
+                        let value = get();
+                        assert!(value.iter().all(|&v| v == value[0]));
+                    }
+                });
+            }
+
+            for i in 1..=200 {
+                reload(vec![i; 16]);
+            }
+            done.store(true, Ordering::SeqCst);
+        });
+        assert_eq!(*get(), vec![200; 16]);
+    }
+
+    #[test]
+    fn reloadable_lazy_reload_under_contention() {
+        let lazy = ReloadableLazy::new(|| vec![0; 16]);
+        reload_under_contention(|| lazy.get(), |value| lazy.reload(value));
+    }
+
+    #[test]
+    fn epoch_lazy_reload_under_contention() {
+        let lazy = EpochLazy::new(|| vec![0; 16]);
+        reload_under_contention(|| lazy.get(), |value| lazy.reload(value));
+    }
+}
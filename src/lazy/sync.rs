@@ -0,0 +1,192 @@
+//! The synchronization primitives used by [`Lazy`](super::Lazy).
+//!
+//! In the library unit tests with the `loom` feature they are built on top of [loom](https://docs.rs/loom), so the loom tests
+//! in `lazy/loom.rs` can explore every interleaving of the threads racing for a `Lazy`.
+//! Loom has no `Once`, so the loom version is a state guarded by a mutex and a condvar with
+//! an atomic `done` flag for the fast path, with the same poisoning rules as `std::sync::Once`.
+//!
+//! Loom primitives can't be created in a `const fn`, so in those tests the constructors
+//! declared with [`const_fn!`] are not `const` and `Lazy` can't be used in a `static`.
+//! Every other target, e.g. the benches and the binary, keeps the std primitives with the feature enabled.
+
+/// Declares a `const fn`, or a plain `fn` in the loom tests
+macro_rules! const_fn {
+    ($(#[$attr:meta])* $vis:vis fn $($rest:tt)*) => {
+        #[cfg(not(all(test, feature = "loom")))]
+        $(#[$attr])* $vis const fn $($rest)*
+
+        #[cfg(all(test, feature = "loom"))]
+        $(#[$attr])* $vis fn $($rest)*
+    };
+}
+
+pub(crate) use const_fn;
+
+#[cfg(not(all(test, feature = "loom")))]
+pub(crate) use self::std_sync::{Once, UnsafeCell};
+
+#[cfg(all(test, feature = "loom"))]
+pub(crate) use self::loom_sync::{Once, UnsafeCell};
+
+#[cfg(not(all(test, feature = "loom")))]
+mod std_sync {
+    /// `std::sync::Once` with a closure without arguments in `call_once_force`, so it can be swapped for the loom one
+    pub(crate) struct Once(std::sync::Once);
+
+    impl Once {
+        pub(crate) const fn new() -> Self {
+            Self(std::sync::Once::new())
+        }
+
+        #[inline]
+        pub(crate) fn is_completed(&self) -> bool {
+            self.0.is_completed()
+        }
+
+        #[inline]
+        pub(crate) fn call_once(&self, f: impl FnOnce()) {
+            self.0.call_once(f);
+        }
+
+        #[inline]
+        pub(crate) fn call_once_force(&self, f: impl FnOnce()) {
+            self.0.call_once_force(|_| f());
+        }
+    }
+
+    /// `std::cell::UnsafeCell` with the closure-based API of `loom::cell::UnsafeCell`
+    pub(crate) struct UnsafeCell<T>(std::cell::UnsafeCell<T>);
+
+    impl<T> UnsafeCell<T> {
+        pub(crate) const fn new(value: T) -> Self {
+            Self(std::cell::UnsafeCell::new(value))
+        }
+
+        #[inline]
+        pub(crate) fn with<R>(&self, f: impl FnOnce(*const T) -> R) -> R {
+            f(self.0.get())
+        }
+
+        #[inline]
+        pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+            f(self.0.get())
+        }
+
+        #[inline]
+        pub(crate) fn get_mut(&mut self) -> &mut T {
+            self.0.get_mut()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "loom"))]
+mod loom_sync {
+    use loom::sync::atomic::{AtomicBool, Ordering};
+    use loom::sync::{Condvar, Mutex};
+
+    /// The state of a loom `Once`
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        Incomplete,
+        Running,
+        Poisoned,
+        Complete,
+    }
+
+    pub(crate) struct Once {
+        /// Set after `state` becomes `Complete`, checked without locking
+        done: AtomicBool,
+        state: Mutex<State>,
+        /// Notified when the closure returns or panics
+        finished: Condvar,
+    }
+
+    impl Once {
+        pub(crate) fn new() -> Self {
+            Self {
+                done: AtomicBool::new(false),
+                state: Mutex::new(State::Incomplete),
+                finished: Condvar::new(),
+            }
+        }
+
+        pub(crate) fn is_completed(&self) -> bool {
+            self.done.load(Ordering::Acquire)
+        }
+
+        pub(crate) fn call_once(&self, f: impl FnOnce()) {
+            self.call(false, f);
+        }
+
+        pub(crate) fn call_once_force(&self, f: impl FnOnce()) {
+            self.call(true, f);
+        }
+
+        fn call(&self, ignore_poisoning: bool, f: impl FnOnce()) {
+            if self.done.load(Ordering::Acquire) {
+                return;
+            }
+
+            // loom mutexes don't support poisoning, so the lock is never held while `f` runs
+            let mut state = self.state.lock().unwrap();
+            loop {
+                match *state {
+                    State::Complete => return,
+                    State::Running => state = self.finished.wait(state).unwrap(),
+                    State::Poisoned if !ignore_poisoning => {
+                        panic!("Once instance has previously been poisoned")
+                    }
+                    State::Incomplete | State::Poisoned => break,
+                }
+            }
+            *state = State::Running;
+            drop(state);
+
+            // stays `Poisoned` if `f` panics
+            let mut finish = Finish {
+                once: self,
+                state: State::Poisoned,
+            };
+            f();
+            finish.state = State::Complete;
+        }
+    }
+
+    /// Sets the final state of a `Once::call` and wakes up the waiting threads when dropped
+    struct Finish<'a> {
+        once: &'a Once,
+        state: State,
+    }
+
+    impl Drop for Finish<'_> {
+        fn drop(&mut self) {
+            *self.once.state.lock().unwrap() = self.state;
+            if self.state == State::Complete {
+                self.once.done.store(true, Ordering::Release);
+            }
+            self.once.finished.notify_all();
+        }
+    }
+
+    /// `loom::cell::UnsafeCell`, every access is checked by loom for data races
+    pub(crate) struct UnsafeCell<T>(loom::cell::UnsafeCell<T>);
+
+    impl<T> UnsafeCell<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Self(loom::cell::UnsafeCell::new(value))
+        }
+
+        pub(crate) fn with<R>(&self, f: impl FnOnce(*const T) -> R) -> R {
+            self.0.with(f)
+        }
+
+        pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+            self.0.with_mut(f)
+        }
+
+        pub(crate) fn get_mut(&mut self) -> &mut T {
+            // SAFETY: `&mut self` guarantees there are no other references to the value
+            self.0.with_mut(|value| unsafe { &mut *value })
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! Lazy values with a fallible initializer, e.g. `regex::Regex::new` without the `unwrap()`.
+//!
+//! There are two ways to deal with an error:
+//! * [`TryLazy`] stores the `Result` of the first attempt forever, same as `Lazy<Result<T, E>>`
+//! * [`RetryLazy`] stores only a successful value and calls the initializer again after an error
+//!
+//! `RetryLazy` returns errors by value. A shared reference to an error cannot be handed out
+//! because the next attempt would have to overwrite it while that reference may still be in use.
+
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use super::sync::const_fn;
+use super::Lazy;
+
+/// A value initialized by a fallible `F` on the first access. The result of the first attempt,
+/// success or error, is returned by every subsequent call.
+///
+/// ```
+/// use rust_benchmarks::lazy::TryLazy;
+///
+/// static COMPILED_REGEX: TryLazy<regex::Regex, regex::Error> = TryLazy::new(|| regex::Regex::new("("));
+///
+/// assert!(COMPILED_REGEX.try_get().is_err());
+/// ```
+pub struct TryLazy<T, E, F = fn() -> Result<T, E>>(Lazy<Result<T, E>, F>);
+
+impl<T, E, F> TryLazy<T, E, F> {
+    const_fn! {
+        /// Creates a new lazy value that will be initialized by `f` on the first access
+        pub fn new(f: F) -> Self {
+            Self(Lazy::new(f))
+        }
+    }
+
+    /// Returns the value if it has been successfully initialized, without initializing it
+    pub fn get(&self) -> Option<&T> {
+        Lazy::get(&self.0).and_then(|result| result.as_ref().ok())
+    }
+}
+
+impl<T, E, F: FnOnce() -> Result<T, E>> TryLazy<T, E, F> {
+    /// Initializes the value if needed and returns a reference to it or to the error
+    /// returned by the initializer
+    pub fn try_get(&self) -> Result<&T, &E> {
+        Lazy::force(&self.0).as_ref()
+    }
+}
+
+impl<T: fmt::Debug, E: fmt::Debug, F> fmt::Debug for TryLazy<T, E, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match Lazy::get(&self.0) {
+            Some(result) => f.debug_tuple("TryLazy").field(result).finish(),
+            None => f.write_str("TryLazy(<uninit>)"),
+        }
+    }
+}
+
+/// A value initialized by a fallible `F` on the first successful attempt.
+/// An error is returned to the caller and the next access calls `F` again.
+///
+/// Attempts are serialized by a mutex, so only one thread runs `F` at a time. A panic in `F`
+/// is propagated to the caller and is treated the same as an error: the next access retries.
+///
+/// ```
+/// use rust_benchmarks::lazy::RetryLazy;
+///
+/// static COMPILED_REGEX: RetryLazy<regex::Regex, regex::Error> = RetryLazy::new(|| regex::Regex::new("^[a-z]+$"));
+///
+/// assert!(COMPILED_REGEX.try_get().unwrap().is_match("abc"));
+/// ```
+pub struct RetryLazy<T, E, F = fn() -> Result<T, E>> {
+    init: F,
+    /// Held by the thread running `init`
+    lock: Mutex<()>,
+    /// Set once `value` is initialized, checked without locking on the fast path
+    done: AtomicBool,
+    value: UnsafeCell<MaybeUninit<T>>,
+    _error: std::marker::PhantomData<fn() -> E>,
+}
+
+// SAFETY: sharing `&RetryLazy` hands out `&T` to all threads, so `T` must be `Sync`, and the value
+// may be created on one thread and dropped on another, so `T` must be `Send`.
+// `F` is only ever called through `&F`, possibly from several threads, so it must be `Sync`.
+// Errors are returned by value to the thread that made the attempt and are never shared.
+unsafe impl<T: Send + Sync, E, F: Sync> Sync for RetryLazy<T, E, F> {}
+
+impl<T, E, F> RetryLazy<T, E, F> {
+    /// Creates a new lazy value that will be initialized by `f` on the first successful attempt
+    pub const fn new(f: F) -> Self {
+        Self {
+            init: f,
+            lock: Mutex::new(()),
+            done: AtomicBool::new(false),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+            _error: std::marker::PhantomData,
+        }
+    }
+
+    /// Returns the value if it has been successfully initialized, without initializing it
+    pub fn get(&self) -> Option<&T> {
+        if self.done.load(Ordering::Acquire) {
+            // SAFETY: `done` is only set after `value` was written and it is never written to again.
+            // The acquire load synchronizes with the release store in `try_get`.
+            Some(unsafe { (*self.value.get()).assume_init_ref() })
+        } else {
+            None
+        }
+    }
+}
+
+impl<T, E, F: Fn() -> Result<T, E>> RetryLazy<T, E, F> {
+    /// Returns the value, initializing it if needed. If the initializer fails
+    /// the error is returned and the next call will try again.
+    pub fn try_get(&self) -> Result<&T, E> {
+        if let Some(value) = self.get() {
+            return Ok(value);
+        }
+
+        // the lock is held while `init` runs, so a panicking attempt poisons it. `done` is only set
+        // after a successful write, so that attempt left nothing behind and this one starts over
+        let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // another thread may have succeeded while this one was waiting for the lock
+        if let Some(value) = self.get() {
+            return Ok(value);
+        }
+
+        let value = (self.init)()?;
+
+        // SAFETY: the lock is held and `done` is not set, so no other thread reads or writes `value`
+        let value = unsafe { (*self.value.get()).write(value) };
+        self.done.store(true, Ordering::Release);
+
+        Ok(value)
+    }
+}
+
+impl<T, E, F> Drop for RetryLazy<T, E, F> {
+    fn drop(&mut self) {
+        if *self.done.get_mut() {
+            // SAFETY: `done` is set, so `value` was initialized and `&mut self` guarantees no references to it
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+impl<T: fmt::Debug, E, F> fmt::Debug for RetryLazy<T, E, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.get() {
+            Some(value) => f.debug_tuple("RetryLazy").field(value).finish(),
+            None => f.write_str("RetryLazy(<uninit>)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::thread;
+
+    /// An unclosed group, fails to compile
+    const INVALID_REGEX: &str = "(";
+
+    // `TryLazy` is a `Lazy`, which can only be used inside a loom model with the `loom` feature.
+    // `RetryLazy` is built on std types, so its tests run either way.
+    #[test]
+    #[cfg(not(feature = "loom"))]
+    #[allow(clippy::invalid_regex)]
+    fn try_lazy_caches_error() {
+        let attempts = AtomicUsize::new(0);
+        let lazy = TryLazy::new(|| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            regex::Regex::new(INVALID_REGEX)
+        });
+
+        assert!(matches!(lazy.try_get(), Err(regex::Error::Syntax(_))));
+        assert!(lazy.try_get().is_err());
+        assert!(lazy.get().is_none());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    #[cfg(not(feature = "loom"))]
+    fn try_lazy_success() {
+        let lazy: TryLazy<_, regex::Error, _> = TryLazy::new(|| regex::Regex::new("^a+$"));
+        assert!(lazy.get().is_none());
+        assert!(lazy.try_get().unwrap().is_match("aaa"));
+        assert!(lazy.get().is_some());
+    }
+
+    #[test]
+    fn retry_lazy_retries_after_error() {
+        let attempts = AtomicUsize::new(0);
+        // fails on the first two attempts
+        let lazy = RetryLazy::new(|| {
+            let pattern = match attempts.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => INVALID_REGEX,
+                _ => "^a+$",
+            };
+            regex::Regex::new(pattern)
+        });
+
+        assert!(lazy.try_get().is_err());
+        assert!(lazy.try_get().is_err());
+        assert!(lazy.get().is_none());
+        assert!(lazy.try_get().unwrap().is_match("aaa"));
+        assert!(lazy.try_get().is_ok());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn retry_lazy_retries_after_panic() {
+        let attempts = AtomicUsize::new(0);
+        let lazy = RetryLazy::new(|| match attempts.fetch_add(1, Ordering::SeqCst) {
+            0 => panic!("first attempt"),
+            n => Ok::<_, ()>(n),
+        });
+
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| lazy.try_get()));
+        assert!(panicked.is_err());
+        assert_eq!(lazy.try_get(), Ok(&1));
+    }
+
+    #[test]
+    fn retry_lazy_initializes_once_across_threads() {
+        static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+        static LAZY: RetryLazy<usize, ()> =
+            RetryLazy::new(|| Ok(ATTEMPTS.fetch_add(1, Ordering::SeqCst)));
+
+        thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| assert_eq!(LAZY.try_get(), Ok(&0)));
+            }
+        });
+        assert_eq!(ATTEMPTS.load(Ordering::SeqCst), 1);
+    }
+}
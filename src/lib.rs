@@ -0,0 +1,25 @@
+//! Supporting code for the `lazy_static!` benchmarks in `benches/`.
+//!
+//! * `corpus`: inputs for the regex benches loaded from the files in `corpus/`
+//! * `executor`: a single-threaded executor for the async benches
+//! * `harness`: a stable-toolchain bench harness
+//! * `lazy`: the hand-rolled lazy static benchmarked next to `lazy_static!` and `once_cell`
+//! * `spin`: a `no_std` lazy static built on a spin lock, behind the `spin` feature
+//! * `workload`: the values the lazy statics in the benches hold and the operations run on them
+//!
+//! Everything but `spin` needs the default `std` feature.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+pub mod corpus;
+#[cfg(feature = "std")]
+pub mod executor;
+#[cfg(feature = "std")]
+pub mod harness;
+#[cfg(feature = "std")]
+pub mod lazy;
+#[cfg(feature = "spin")]
+pub mod spin;
+#[cfg(feature = "std")]
+pub mod workload;
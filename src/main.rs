@@ -9,8 +9,10 @@
 // copied, modified, or distributed except according to those terms.
 
 use core::ops::Deref;
-use std::cell::Cell;
-use std::sync::Once;
+use rust_benchmarks::harness::{export, html, readme};
+use rust_benchmarks::lazy::{InitStats, Lazy};
+use std::path::{Path, PathBuf};
+use std::process;
 
 /// A test regex to validate an email address - used here for a test
 const LONG_REGEX: &str = r#"[a-z0-9!#$%&'*+/=?^_`{|}~-]+(?:\.[a-z0-9!#$%&'*+/=?^_`{|}~-]+)*@(?:[a-z0-9](?:[a-z0-9-]*[a-z0-9])?\.)+[a-z0-9](?:[a-z0-9-]*[a-z0-9])?"#;
@@ -19,14 +21,6 @@ const TEST_EMAIL: &str = "name@example.com";
 /// An invalid email for
 const TEST_NOT_EMAIL: &str = "Hello world!";
 
-/// A container for lazy initialization of a compiled version of LONG_REGEX
-struct Lazy<T: Sync>(Cell<Option<T>>, Once);
-
-// This line is required because
-// `Cell<Option<regex::Regex>>` cannot be shared between threads safely within `Lazy<regex::Regex>`, the trait `Sync` is not implemented for `Cell<Option<regex::Regex>>`
-// Shared static variables must have a type that implements `Sync`rustc [E0277](https://doc.rust-lang.org/error-index.html#E0277)
-unsafe impl<T: Sync> Sync for Lazy<T> {}
-
 /// A structure with a hidden variable to store the compiled regex
 struct CompiledRegex {
     __private_field: (),
@@ -37,37 +31,49 @@ static COMPILED_REGEX: CompiledRegex = CompiledRegex {
     __private_field: (),
 };
 
+/// What happened to COMPILED_REGEX, recorded only with `--features observe`
+static COMPILED_REGEX_STATS: InitStats = InitStats::new();
+
 // This Deref implementation initializes the hidden variable with the compiled regex on the first run
 // and returns its value on subsequent runs.
 impl Deref for CompiledRegex {
     type Target = regex::Regex;
     fn deref(&self) -> &regex::Regex {
-        static LAZY: Lazy<regex::Regex> = Lazy(Cell::new(None), Once::new());
-
-        println!("Derefencing CompiledRegex");
+        // A container for lazy initialization of a compiled version of LONG_REGEX.
+        // See src/lazy/mod.rs for how it works.
+        // Every deref and the initialization are counted in COMPILED_REGEX_STATS.
+        static LAZY: Lazy<regex::Regex> = Lazy::with_observer(
+            || regex::Regex::new(LONG_REGEX).unwrap(),
+            &COMPILED_REGEX_STATS,
+        );
 
-        // Performs an initialization routine once and only once.
-        // The given closure will be executed if this is the first time call_once has been called, and otherwise the routine will not be invoked.
-        // https://doc.rust-lang.org/std/sync/struct.Once.html#method.call_once
-        LAZY.1.call_once(|| {
-            LAZY.0.set(Some(regex::Regex::new(LONG_REGEX).unwrap()));
-            println!("CompiledRegex initialized");
-        });
-
-        // `self.0` is guaranteed to be `Some` by this point
-        // The `Once` will catch and propagate panics
-        unsafe {
-            match *LAZY.0.as_ptr() {
-                Some(ref x) => x,
-                None => {
-                    panic!("attempted to dereference an uninitialized lazy static. This is a bug");
-                }
-            }
-        }
+        // Performs the initialization once and only once using `std::sync::Once` inside `Lazy`
+        // and returns a reference to the initialized value on every call.
+        // If the initializer panics the panic is propagated and every later call panics as well,
+        // see benches/poisoning/mod.rs.
+        Lazy::force(&LAZY)
     }
 }
 
 fn main() {
+    // the commands work with the results of `cargo bench` instead of running the demo:
+    // * `readme [RESULTS_JSON]` regenerates the results table in README.md
+    // * `html [RESULTS_JSON]` renders the results as an HTML report
+    // * `html --baselines BASE NEW` renders the comparison of two baselines as an HTML report
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("readme") => return update_readme(args.next().map(PathBuf::from)),
+        Some("html") => return write_html(args.collect()),
+        Some(command) => {
+            eprintln!(
+                "unknown command {command}, the commands are `readme [RESULTS_JSON]`, \
+                 `html [RESULTS_JSON]` and `html --baselines BASE NEW`"
+            );
+            process::exit(2);
+        }
+        None => {}
+    }
+
     // at this point COMPILED_REGEX is not initialized
     println!("Program started");
 
@@ -82,4 +88,63 @@ fn main() {
         "{TEST_NOT_EMAIL} is valid: {}",
         COMPILED_REGEX.is_match(TEST_NOT_EMAIL)
     );
+
+    print_report("COMPILED_REGEX", &COMPILED_REGEX_STATS);
+}
+
+/// Regenerates the results table in README.md from `results`, the latest results by default
+fn update_readme(results: Option<PathBuf>) {
+    let updated = results
+        .map_or_else(readme::latest_results, Ok)
+        .and_then(|results| {
+            readme::update(Path::new("README.md"), &results)?;
+            Ok(results)
+        });
+    match updated {
+        Ok(results) => println!("README.md updated from {}", results.display()),
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    }
+}
+
+/// Writes the HTML report of the results or of the comparison of two baselines
+fn write_html(args: Vec<String>) {
+    let written = match args.as_slice() {
+        [flag, base, new] if flag == "--baselines" => {
+            html::write_comparison(Path::new(export::DEFAULT_RESULTS_DIR), base, new)
+        }
+        [results] => html::write_report(Path::new(results)),
+        [] => readme::latest_results().and_then(|results| html::write_report(&results)),
+        _ => {
+            eprintln!("usage: `html [RESULTS_JSON]` or `html --baselines BASE NEW`");
+            process::exit(2);
+        }
+    };
+    match written {
+        Ok(path) => println!("report written to {}", path.display()),
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    }
+}
+
+/// Prints the derefs and initializations recorded in `stats`
+fn print_report(name: &str, stats: &InitStats) {
+    if !cfg!(feature = "observe") {
+        println!("{name}: run with `--features observe` to count the derefs and initializations");
+        return;
+    }
+
+    let report = stats.report();
+    println!("{name}:");
+    println!("  derefs:         {}", report.derefs);
+    println!("  inits:          {}", report.inits);
+    println!("  init duration:  {:?}", report.init_duration);
+    match report.init_thread {
+        Some(thread) => println!("  init thread:    {thread:?}"),
+        None => println!("  init thread:    -"),
+    }
 }
@@ -0,0 +1,216 @@
+//! A `no_std` lazy value, the same idea as `lazy::Lazy` without `std::sync::Once`.
+//!
+//! The state is a single atomic byte moving from `UNINIT` to `RUNNING` to `DONE`. The thread that
+//! moves it to `RUNNING` calls the initializer, the others spin until it is `DONE`. Spinning burns
+//! CPU time while the initializer runs, which is fine for a short initializer on a target without
+//! an OS to park the waiting threads.
+//!
+//! Only uses `core`, so it is available with `--no-default-features --features spin`.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::hint;
+use core::mem::MaybeUninit;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// Nobody has started the initialization yet
+const UNINIT: u8 = 0;
+/// A thread is running the initializer
+const RUNNING: u8 = 1;
+/// `value` is initialized
+const DONE: u8 = 2;
+/// The initializer panicked, every access panics as well, same as `lazy::Poison`
+const POISONED: u8 = 3;
+
+/// A value initialized by `F` on the first access, safe to use in a `static` without `std`.
+///
+/// ```
+/// use rust_benchmarks::spin::SpinLazy;
+///
+/// static POWERS_OF_TWO: SpinLazy<[u64; 64]> = SpinLazy::new(|| core::array::from_fn(|i| 1 << i));
+///
+/// assert_eq!(POWERS_OF_TWO[10], 1024);
+/// ```
+pub struct SpinLazy<T, F = fn() -> T> {
+    state: AtomicU8,
+    /// Taken out and called by the thread that moves `state` to `RUNNING`
+    init: UnsafeCell<Option<F>>,
+    /// Initialized if and only if `state` is `DONE`
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// SAFETY: same as `Lazy`, `&T` is handed out to all threads, so `T` must be `Sync`, and the value
+// may be created on one thread and dropped on another, so `T` must be `Send`. `F` may be called
+// on any thread, so it must be `Send`. Access to `init` and `value` is synchronized by `state`.
+unsafe impl<T: Send + Sync, F: Send> Sync for SpinLazy<T, F> {}
+
+impl<T, F> SpinLazy<T, F> {
+    /// Creates a new lazy value that will be initialized by `f` on the first access
+    pub const fn new(f: F) -> Self {
+        Self {
+            state: AtomicU8::new(UNINIT),
+            init: UnsafeCell::new(Some(f)),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Returns the value if it has been initialized, without initializing it
+    pub fn get(this: &Self) -> Option<&T> {
+        if this.state.load(Ordering::Acquire) == DONE {
+            // SAFETY: `state` is `DONE`, so `value` was initialized and is never written to again.
+            // The acquire load synchronizes with the release store in `initialize`.
+            Some(unsafe { (*this.value.get()).assume_init_ref() })
+        } else {
+            None
+        }
+    }
+}
+
+impl<T, F: FnOnce() -> T> SpinLazy<T, F> {
+    /// Initializes the value if needed and returns a reference to it.
+    ///
+    /// If the initializer panics the panic is propagated to the caller and every subsequent
+    /// access panics as well.
+    pub fn force(this: &Self) -> &T {
+        match Self::get(this) {
+            Some(value) => value,
+            None => this.initialize(),
+        }
+    }
+
+    #[cold]
+    fn initialize(&self) -> &T {
+        match self
+            .state
+            .compare_exchange(UNINIT, RUNNING, Ordering::Acquire, Ordering::Acquire)
+        {
+            Ok(_) => {
+                // moves `state` to `POISONED` if the initializer panics, so the waiting threads don't spin forever
+                let poison = PoisonOnUnwind(&self.state);
+
+                // SAFETY: only the thread that moved `state` from `UNINIT` to `RUNNING` gets here and the
+                // others don't touch `init` or `value` until `state` is `DONE`
+                let init = unsafe { (*self.init.get()).take() };
+                let init = init.expect("the initializer of SpinLazy has already been taken");
+                let value = unsafe { (*self.value.get()).write(init()) };
+
+                core::mem::forget(poison);
+                self.state.store(DONE, Ordering::Release);
+                value
+            }
+            Err(_) => loop {
+                match self.state.load(Ordering::Acquire) {
+                    // SAFETY: the acquire load synchronizes with the release store above
+                    DONE => return unsafe { (*self.value.get()).assume_init_ref() },
+                    POISONED => panic!("SpinLazy instance has previously been poisoned"),
+                    _ => hint::spin_loop(),
+                }
+            },
+        }
+    }
+}
+
+/// Sets the state to `POISONED` when dropped, i.e. unless it is forgotten after the initializer returns
+struct PoisonOnUnwind<'a>(&'a AtomicU8);
+
+impl Drop for PoisonOnUnwind<'_> {
+    fn drop(&mut self) {
+        self.0.store(POISONED, Ordering::Release);
+    }
+}
+
+impl<T, F: FnOnce() -> T> Deref for SpinLazy<T, F> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        SpinLazy::force(self)
+    }
+}
+
+impl<T, F> Drop for SpinLazy<T, F> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == DONE {
+            // SAFETY: `state` is `DONE`, so `value` was initialized and `&mut self` guarantees no references to it
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+impl<T: fmt::Debug, F> fmt::Debug for SpinLazy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match SpinLazy::get(self) {
+            Some(value) => f.debug_tuple("SpinLazy").field(value).finish(),
+            None => f.write_str("SpinLazy(<uninit>)"),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    #[test]
+    fn initializes_once() {
+        let count = AtomicUsize::new(0);
+        let lazy = SpinLazy::new(|| count.fetch_add(1, Ordering::SeqCst) + 42);
+
+        assert_eq!(SpinLazy::get(&lazy), None);
+        assert_eq!(format!("{lazy:?}"), "SpinLazy(<uninit>)");
+        assert_eq!(*lazy, 42);
+        assert_eq!(*SpinLazy::force(&lazy), 42);
+        assert_eq!(format!("{lazy:?}"), "SpinLazy(42)");
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn initializes_once_across_threads() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        static LAZY: SpinLazy<usize> = SpinLazy::new(|| {
+            // long enough for the other threads to find it running
+            thread::sleep(std::time::Duration::from_millis(10));
+            COUNT.fetch_add(1, Ordering::SeqCst) + 42
+        });
+
+        let barrier = Barrier::new(8);
+        thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    barrier.wait();
+                    assert_eq!(*LAZY, 42);
+                });
+            }
+        });
+        assert_eq!(COUNT.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn drops_value_only_if_initialized() {
+        let value = Arc::new(());
+
+        let lazy: SpinLazy<Arc<()>, _> = SpinLazy::new(|| Arc::clone(&value));
+        drop(lazy);
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        let lazy = SpinLazy::new(|| Arc::clone(&value));
+        SpinLazy::force(&lazy);
+        assert_eq!(Arc::strong_count(&value), 2);
+        drop(lazy);
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+
+    #[test]
+    fn poisoned_after_panic() {
+        let lazy: SpinLazy<u32, _> = SpinLazy::new(|| panic!("first attempt"));
+
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| *lazy)).is_err());
+        let second = panic::catch_unwind(AssertUnwindSafe(|| *lazy)).unwrap_err();
+        assert_eq!(
+            second.downcast_ref::<&str>(),
+            Some(&"SpinLazy instance has previously been poisoned")
+        );
+    }
+}
@@ -0,0 +1,442 @@
+//! The values the lazy statics in the benches hold and what is done with them on every access.
+//!
+//! A [`Workload`] separates the cost of the lazy access from the cost of the payload: the same
+//! strategies are benched with a compiled regex, a large lookup table, a parsed config,
+//! a precomputed CRC table and a plain `u64`, where the access check is all there is to measure.
+//!
+//! The regex is also used through other APIs than `is_match`, which cost more or less per access:
+//! [`EmailFind`], [`EmailCaptures`], [`EmailFindIter`], [`EmailSet`] and [`EmailBytes`].
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Finds email addresses. Taken from https://github.com/rust-lang/regex/blob/master/tests/crazy.rs
+pub const LONG_REGEX: &str = r#"[a-z0-9!#$%&'*+/=?^_`{|}~-]+(?:\.[a-z0-9!#$%&'*+/=?^_`{|}~-]+)*@(?:[a-z0-9](?:[a-z0-9-]*[a-z0-9])?\.)+[a-z0-9](?:[a-z0-9-]*[a-z0-9])?"#;
+pub const TEST_EMAIL: &str = "max@example.com";
+
+/// A value built once and stored in a lazy static, and the operation run on it on every access
+pub trait Workload {
+    /// The name in the bench names and the results, e.g. `lookup_table`
+    const NAME: &'static str;
+    /// What the lazy static holds, `Clone` for the strategies that copy it into thread-local storage
+    type Value: Clone + Send + Sync + 'static;
+    type Output;
+    /// Where [`run`](Workload::run) takes its input from if it isn't a constant, e.g. the position
+    /// in a list of inputs. Each bench creates its own outside of the measured loop, so picking
+    /// the input costs the same for every strategy and doesn't add a lazy access or an atomic.
+    type Cursor: Default;
+
+    /// Builds the value, what the lazy static runs on the first access
+    fn build() -> Self::Value;
+    /// The operation measured on every access
+    fn run(value: &Self::Value, cursor: &mut Self::Cursor) -> Self::Output;
+    /// Whether `output` is what [`run`](Workload::run) is expected to return
+    fn verify(output: &Self::Output) -> bool;
+}
+
+/// [`LONG_REGEX`] matched against [`TEST_EMAIL`], what the benches measured before there were workloads
+pub struct EmailRegex;
+
+impl Workload for EmailRegex {
+    const NAME: &'static str = "email_regex";
+    type Value = regex::Regex;
+    type Output = bool;
+    type Cursor = ();
+
+    fn build() -> regex::Regex {
+        regex::Regex::new(LONG_REGEX).unwrap()
+    }
+
+    fn run(regex: &regex::Regex, _: &mut ()) -> bool {
+        regex.is_match(TEST_EMAIL)
+    }
+
+    fn verify(is_match: &bool) -> bool {
+        *is_match
+    }
+}
+
+/// An email within a sentence, for the regex APIs that return where the match is
+pub const TEST_SENTENCE: &str =
+    "Contact max@example.com before 2025-10-18 or see https://example.com/contact";
+/// [`LONG_REGEX`] with the local part and the domain in capture groups
+pub const CAPTURING_REGEX: &str = r#"([a-z0-9!#$%&'*+/=?^_`{|}~-]+(?:\.[a-z0-9!#$%&'*+/=?^_`{|}~-]+)*)@((?:[a-z0-9](?:[a-z0-9-]*[a-z0-9])?\.)+[a-z0-9](?:[a-z0-9-]*[a-z0-9])?)"#;
+/// A paragraph with five emails for `find_iter`
+pub const DOCUMENT: &str =
+    "Alice <alice@example.com> wrote to bob@example.org and carol.smith@mail.example.net, \
+                            copying admin@example.io. Replies go to noreply@example.com, \
+                            not to the list at lists.example.com.";
+/// The patterns of the [`RegexSet`](regex::RegexSet) in [`EmailSet`]: an email, a URL, an ISO date and an IPv4 address
+pub const SET_PATTERNS: [&str; 4] = [
+    LONG_REGEX,
+    r"https?://[^\s]+",
+    r"[0-9]{4}-[0-9]{2}-[0-9]{2}",
+    r"\b(?:[0-9]{1,3}\.){3}[0-9]{1,3}\b",
+];
+/// Where the email is in [`TEST_SENTENCE`]
+const SENTENCE_EMAIL: Range<usize> = 8..23;
+
+/// `Regex::find` of [`LONG_REGEX`] in [`TEST_SENTENCE`]
+pub struct EmailFind;
+
+impl Workload for EmailFind {
+    const NAME: &'static str = "regex_find";
+    type Value = regex::Regex;
+    type Output = Option<Range<usize>>;
+    type Cursor = ();
+
+    fn build() -> regex::Regex {
+        EmailRegex::build()
+    }
+
+    fn run(regex: &regex::Regex, _: &mut ()) -> Option<Range<usize>> {
+        regex.find(TEST_SENTENCE).map(|m| m.range())
+    }
+
+    fn verify(found: &Option<Range<usize>>) -> bool {
+        *found == Some(SENTENCE_EMAIL)
+    }
+}
+
+/// `Regex::captures` of [`CAPTURING_REGEX`] in [`TEST_SENTENCE`], the local part and the domain
+pub struct EmailCaptures;
+
+impl Workload for EmailCaptures {
+    const NAME: &'static str = "regex_captures";
+    type Value = regex::Regex;
+    type Output = Option<(Range<usize>, Range<usize>)>;
+    type Cursor = ();
+
+    fn build() -> regex::Regex {
+        regex::Regex::new(CAPTURING_REGEX).unwrap()
+    }
+
+    fn run(regex: &regex::Regex, _: &mut ()) -> Option<(Range<usize>, Range<usize>)> {
+        let captures = regex.captures(TEST_SENTENCE)?;
+        Some((captures.get(1)?.range(), captures.get(2)?.range()))
+    }
+
+    fn verify(groups: &Option<(Range<usize>, Range<usize>)>) -> bool {
+        // `max` and `example.com`
+        *groups == Some((8..11, 12..23))
+    }
+}
+
+/// `Regex::find_iter` of [`LONG_REGEX`] over the whole [`DOCUMENT`], the number of emails in it
+pub struct EmailFindIter;
+
+impl Workload for EmailFindIter {
+    const NAME: &'static str = "regex_find_iter";
+    type Value = regex::Regex;
+    type Output = usize;
+    type Cursor = ();
+
+    fn build() -> regex::Regex {
+        EmailRegex::build()
+    }
+
+    fn run(regex: &regex::Regex, _: &mut ()) -> usize {
+        regex.find_iter(DOCUMENT).count()
+    }
+
+    fn verify(count: &usize) -> bool {
+        *count == 5
+    }
+}
+
+/// `RegexSet::matches` of [`SET_PATTERNS`] in [`TEST_SENTENCE`], a bit per matching pattern
+pub struct EmailSet;
+
+impl Workload for EmailSet {
+    const NAME: &'static str = "regex_set";
+    type Value = regex::RegexSet;
+    type Output = u32;
+    type Cursor = ();
+
+    fn build() -> regex::RegexSet {
+        regex::RegexSet::new(SET_PATTERNS).unwrap()
+    }
+
+    fn run(set: &regex::RegexSet, _: &mut ()) -> u32 {
+        set.matches(TEST_SENTENCE)
+            .iter()
+            .fold(0, |bits, pattern| bits | 1 << pattern)
+    }
+
+    fn verify(bits: &u32) -> bool {
+        // the email, the URL and the date, but no IP address
+        *bits == 0b0111
+    }
+}
+
+/// [`LONG_REGEX`] as `regex::bytes::Regex` matched against the bytes of [`TEST_EMAIL`]
+pub struct EmailBytes;
+
+impl Workload for EmailBytes {
+    const NAME: &'static str = "regex_bytes";
+    type Value = regex::bytes::Regex;
+    type Output = bool;
+    type Cursor = ();
+
+    fn build() -> regex::bytes::Regex {
+        regex::bytes::Regex::new(LONG_REGEX).unwrap()
+    }
+
+    fn run(regex: &regex::bytes::Regex, _: &mut ()) -> bool {
+        regex.is_match(TEST_EMAIL.as_bytes())
+    }
+
+    fn verify(is_match: &bool) -> bool {
+        *is_match
+    }
+}
+
+/// The number of entries in the [`LookupTable`]
+pub const TABLE_SIZE: u64 = 100_000;
+const LOOKUP_KEY: u64 = 77_777;
+
+/// A single lookup in a `HashMap` with [`TABLE_SIZE`] entries, each key mapped to its square
+pub struct LookupTable;
+
+impl Workload for LookupTable {
+    const NAME: &'static str = "lookup_table";
+    type Value = HashMap<u64, u64>;
+    type Output = Option<u64>;
+    type Cursor = ();
+
+    fn build() -> HashMap<u64, u64> {
+        (0..TABLE_SIZE).map(|key| (key, key * key)).collect()
+    }
+
+    fn run(table: &HashMap<u64, u64>, _: &mut ()) -> Option<u64> {
+        table.get(&LOOKUP_KEY).copied()
+    }
+
+    fn verify(value: &Option<u64>) -> bool {
+        *value == Some(LOOKUP_KEY * LOOKUP_KEY)
+    }
+}
+
+/// The text [`Config`] is parsed from, `key = value` lines with `#` comments
+pub const CONFIG: &str = r#"
+# the service the benches pretend to configure
+name = "lazy-bench"
+workers = 8
+timeout_ms = 1500
+hosts = "a.example.com, b.example.com, c.example.com"
+verbose = true
+"#;
+const TEST_HOST: &str = "b.example.com";
+
+/// A config struct parsed from [`CONFIG`], every access checks whether a host is configured
+#[derive(Clone, PartialEq, Debug)]
+pub struct Config {
+    pub name: String,
+    pub workers: usize,
+    pub timeout_ms: u64,
+    pub hosts: Vec<String>,
+    pub verbose: bool,
+}
+
+impl Config {
+    /// Parses `key = value` lines, every key is required and unknown keys are an error
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut values = HashMap::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("expected `key = value`, got `{line}`"))?;
+            values.insert(key.trim(), value.trim().trim_matches('"'));
+        }
+
+        let mut take = |key: &str| values.remove(key).ok_or_else(|| format!("missing `{key}`"));
+        let number = |key: &str, value: &str| {
+            value
+                .parse::<u64>()
+                .map_err(|e| format!("invalid `{key}`: {e}"))
+        };
+        let config = Self {
+            name: take("name")?.to_owned(),
+            workers: number("workers", take("workers")?)? as usize,
+            timeout_ms: number("timeout_ms", take("timeout_ms")?)?,
+            hosts: take("hosts")?
+                .split(',')
+                .map(|host| host.trim().to_owned())
+                .collect(),
+            verbose: take("verbose")?
+                .parse()
+                .map_err(|e| format!("invalid `verbose`: {e}"))?,
+        };
+        match values.keys().next() {
+            Some(key) => Err(format!("unknown key `{key}`")),
+            None => Ok(config),
+        }
+    }
+}
+
+impl Workload for Config {
+    const NAME: &'static str = "config";
+    type Value = Config;
+    type Output = bool;
+    type Cursor = ();
+
+    fn build() -> Config {
+        Config::parse(CONFIG).unwrap()
+    }
+
+    fn run(config: &Config, _: &mut ()) -> bool {
+        config.hosts.iter().any(|host| host == TEST_HOST)
+    }
+
+    fn verify(is_configured: &bool) -> bool {
+        *is_configured
+    }
+}
+
+/// The CRC-32 of [`TEST_EMAIL`] computed with a precomputed table of the 256 byte remainders
+pub struct CrcTable;
+
+/// The reversed polynomial of CRC-32 as used by zlib, Ethernet and PNG
+const CRC32_POLYNOMIAL: u32 = 0xEDB8_8320;
+/// `zlib.crc32(b"max@example.com")`
+const TEST_EMAIL_CRC32: u32 = 0x75D8_BD1B;
+
+impl Workload for CrcTable {
+    const NAME: &'static str = "crc_table";
+    type Value = [u32; 256];
+    type Output = u32;
+    type Cursor = ();
+
+    fn build() -> [u32; 256] {
+        let mut table = [0; 256];
+        for (byte, remainder) in table.iter_mut().enumerate() {
+            *remainder = (0..8).fold(byte as u32, |crc, _| {
+                if crc & 1 == 1 {
+                    (crc >> 1) ^ CRC32_POLYNOMIAL
+                } else {
+                    crc >> 1
+                }
+            });
+        }
+        table
+    }
+
+    fn run(table: &[u32; 256], _: &mut ()) -> u32 {
+        !TEST_EMAIL.bytes().fold(!0, |crc, byte| {
+            table[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+        })
+    }
+
+    fn verify(crc: &u32) -> bool {
+        *crc == TEST_EMAIL_CRC32
+    }
+}
+
+/// A `u64` read from the lazy static, nothing but the cost of the access itself
+pub struct Trivial;
+
+const TRIVIAL_VALUE: u64 = 0x5EED;
+
+impl Workload for Trivial {
+    const NAME: &'static str = "u64";
+    type Value = u64;
+    type Output = u64;
+    type Cursor = ();
+
+    fn build() -> u64 {
+        TRIVIAL_VALUE
+    }
+
+    fn run(value: &u64, _: &mut ()) -> u64 {
+        *value
+    }
+
+    fn verify(value: &u64) -> bool {
+        *value == TRIVIAL_VALUE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check<W: Workload>() {
+        assert!(
+            W::verify(&W::run(&W::build(), &mut W::Cursor::default())),
+            "{}",
+            W::NAME
+        );
+    }
+
+    #[test]
+    fn workloads_test() {
+        check::<EmailRegex>();
+        check::<EmailFind>();
+        check::<EmailCaptures>();
+        check::<EmailFindIter>();
+        check::<EmailSet>();
+        check::<EmailBytes>();
+        check::<LookupTable>();
+        check::<Config>();
+        check::<CrcTable>();
+        check::<Trivial>();
+
+        assert_eq!(LookupTable::build().len(), TABLE_SIZE as usize);
+        assert!(!CrcTable::verify(&0));
+    }
+
+    #[test]
+    fn regex_apis_agree() {
+        let regex = EmailRegex::build();
+        let found = EmailFind::run(&regex, &mut ()).unwrap();
+        assert_eq!(&TEST_SENTENCE[found.clone()], "max@example.com");
+
+        let captures = EmailCaptures::build().captures(TEST_SENTENCE).unwrap();
+        assert_eq!(captures.get(0).unwrap().range(), found);
+        let bytes = EmailBytes::build();
+        assert_eq!(bytes.find(TEST_SENTENCE.as_bytes()).unwrap().range(), found);
+
+        // find_iter finds the same emails as find in each of them
+        let emails: Vec<&str> = regex.find_iter(DOCUMENT).map(|m| m.as_str()).collect();
+        assert_eq!(emails.len(), EmailFindIter::run(&regex, &mut ()));
+        assert!(emails
+            .iter()
+            .all(|email| regex.find(email).unwrap().as_str() == *email));
+
+        // every pattern of the set on its own
+        let bits = EmailSet::run(&EmailSet::build(), &mut ());
+        for (i, pattern) in SET_PATTERNS.iter().enumerate() {
+            let is_match = regex::Regex::new(pattern).unwrap().is_match(TEST_SENTENCE);
+            assert_eq!(bits & 1 << i != 0, is_match, "{pattern}");
+        }
+    }
+
+    #[test]
+    fn config_test() {
+        let config = Config::build();
+        assert_eq!(config.name, "lazy-bench");
+        assert_eq!((config.workers, config.timeout_ms), (8, 1500));
+        assert_eq!(config.hosts.len(), 3);
+        assert!(config.verbose);
+
+        assert_eq!(
+            Config::parse("workers 8").unwrap_err(),
+            "expected `key = value`, got `workers 8`"
+        );
+        assert_eq!(
+            Config::parse(&CONFIG.replace("workers = 8", "workers = many")).unwrap_err(),
+            "invalid `workers`: invalid digit found in string"
+        );
+        assert_eq!(
+            Config::parse(&CONFIG.replace("verbose = true", "")).unwrap_err(),
+            "missing `verbose`"
+        );
+        assert_eq!(
+            Config::parse(&format!("{CONFIG}color = red")).unwrap_err(),
+            "unknown key `color`"
+        );
+    }
+}
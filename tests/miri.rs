@@ -0,0 +1,272 @@
+//! Exercises every `unsafe` block in the lazy types with a cheap payload instead of `regex::Regex`,
+//! which takes minutes to compile under Miri. Run with
+//! `cargo +nightly miri test --test miri --features spin`.
+//!
+//! The payload owns a heap allocation, so Miri reports a read of an uninitialized, freed or
+//! aliased value, and counts its drops, so the tests can check every value is dropped exactly once.
+//! It also runs on the stable toolchain with `cargo test`, as a regular test.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use rust_benchmarks::lazy::{EpochLazy, Lazy, ReloadableLazy, RetryLazy, TryLazy};
+
+/// The number of threads racing for the same value, kept low because Miri is slow
+const THREADS: usize = 4;
+
+/// A stand-in for `regex::Regex`: a heap allocation to read through and a shared drop counter
+#[derive(Debug)]
+struct Payload {
+    value: Box<u32>,
+    drops: Arc<AtomicUsize>,
+}
+
+impl Payload {
+    fn new(value: u32, drops: &Arc<AtomicUsize>) -> Self {
+        Self {
+            value: Box::new(value),
+            drops: Arc::clone(drops),
+        }
+    }
+
+    fn value(&self) -> u32 {
+        *self.value
+    }
+}
+
+impl Drop for Payload {
+    fn drop(&mut self) {
+        self.drops.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+fn drop_counter() -> Arc<AtomicUsize> {
+    Arc::new(AtomicUsize::new(0))
+}
+
+/// Calls `f` and returns `Err` if it panicked
+fn catch_panic<R>(f: impl FnOnce() -> R) -> Result<R, ()> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(drop)
+}
+
+#[test]
+fn lazy_get_force_drop() {
+    let drops = drop_counter();
+    let lazy = Lazy::new(|| Payload::new(42, &drops));
+
+    assert!(Lazy::get(&lazy).is_none());
+    let first = Lazy::force(&lazy);
+    // a second reference while the first one is alive, both point at the same value
+    let second = Lazy::get(&lazy).unwrap();
+    assert_eq!(first.value() + second.value(), 84);
+    assert!(std::ptr::eq(first, second));
+
+    drop(lazy);
+    assert_eq!(drops.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn lazy_uninit_drop() {
+    let drops = drop_counter();
+    let lazy: Lazy<Payload, _> = Lazy::new(|| Payload::new(42, &drops));
+    drop(lazy);
+    assert_eq!(drops.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn lazy_concurrent_force() {
+    let drops = drop_counter();
+    let lazy = Lazy::new(|| Payload::new(42, &drops));
+
+    thread::scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|| assert_eq!(lazy.value(), 42));
+        }
+        // reads on this thread while the others may be initializing it
+        if let Some(payload) = Lazy::get(&lazy) {
+            assert_eq!(payload.value(), 42);
+        }
+    });
+
+    drop(lazy);
+    assert_eq!(drops.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn lazy_poisoned() {
+    let lazy: Lazy<Payload, _> = Lazy::new(|| panic!("first attempt"));
+    assert!(catch_panic(|| Lazy::force(&lazy).value()).is_err());
+    assert!(catch_panic(|| Lazy::force(&lazy).value()).is_err());
+    assert!(Lazy::get(&lazy).is_none());
+}
+
+#[test]
+fn lazy_retry_after_panic() {
+    let drops = drop_counter();
+    let attempts = AtomicUsize::new(0);
+    let lazy = Lazy::with_retry(|| {
+        if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+            panic!("first attempt");
+        }
+        Payload::new(42, &drops)
+    });
+
+    assert!(catch_panic(|| lazy.value()).is_err());
+    assert_eq!(lazy.value(), 42);
+    assert_eq!(lazy.value(), 42);
+
+    drop(lazy);
+    assert_eq!(drops.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn try_lazy_ok_and_err() {
+    let drops = drop_counter();
+
+    let ok: TryLazy<Payload, Payload, _> = TryLazy::new(|| Ok(Payload::new(42, &drops)));
+    assert_eq!(ok.try_get().unwrap().value(), 42);
+    assert_eq!(ok.get().unwrap().value(), 42);
+
+    let err: TryLazy<Payload, Payload, _> = TryLazy::new(|| Err(Payload::new(7, &drops)));
+    assert_eq!(err.try_get().unwrap_err().value(), 7);
+    assert!(err.get().is_none());
+
+    drop((ok, err));
+    assert_eq!(drops.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn retry_lazy_error_then_value() {
+    let drops = drop_counter();
+    let attempts = AtomicUsize::new(0);
+    let lazy = RetryLazy::new(|| match attempts.fetch_add(1, Ordering::SeqCst) {
+        0 => Err(()),
+        _ => Ok(Payload::new(42, &drops)),
+    });
+
+    assert!(lazy.try_get().is_err());
+    assert!(lazy.get().is_none());
+    assert_eq!(lazy.try_get().unwrap().value(), 42);
+    assert_eq!(lazy.get().unwrap().value(), 42);
+
+    drop(lazy);
+    assert_eq!(drops.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn retry_lazy_concurrent_try_get() {
+    let drops = drop_counter();
+    let lazy: RetryLazy<Payload, (), _> = RetryLazy::new(|| Ok(Payload::new(42, &drops)));
+
+    thread::scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|| assert_eq!(lazy.try_get().unwrap().value(), 42));
+        }
+    });
+
+    drop(lazy);
+    assert_eq!(drops.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn reloadable_lazy_reload() {
+    let drops = drop_counter();
+    let lazy = ReloadableLazy::new(|| Payload::new(42, &drops));
+
+    assert_eq!(lazy.get().value(), 42);
+    lazy.reload(Payload::new(7, &drops));
+    assert_eq!(lazy.get().value(), 7);
+    assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+    drop(lazy);
+    assert_eq!(drops.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn epoch_lazy_reload_and_drop() {
+    let drops = drop_counter();
+    let lazy = EpochLazy::new(|| Payload::new(42, &drops));
+
+    // reloading before the first access doesn't leak the reloaded value
+    let uninit: EpochLazy<Payload, fn() -> Payload> = EpochLazy::new(|| unreachable!());
+    uninit.reload(Payload::new(1, &drops));
+    assert_eq!(uninit.get().value(), 1);
+    drop(uninit);
+    assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+    assert_eq!(lazy.get().value(), 42);
+    lazy.reload(Payload::new(7, &drops));
+    assert_eq!(lazy.get().value(), 7);
+    assert_eq!(drops.load(Ordering::SeqCst), 2);
+
+    drop(lazy);
+    assert_eq!(drops.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn epoch_lazy_reload_while_reading() {
+    let drops = drop_counter();
+    let lazy = EpochLazy::new(|| Payload::new(0, &drops));
+    // initialized up front, so every reload below frees a value
+    assert_eq!(lazy.get().value(), 0);
+
+    thread::scope(|s| {
+        for _ in 0..THREADS {
+            s.spawn(|| {
+                for _ in 0..4 {
+                    // the old value must stay alive until the guard is dropped
+                    let guard = lazy.get();
+                    assert!(guard.value() <= 4);
+                }
+            });
+        }
+        for value in 1..=4 {
+            lazy.reload(Payload::new(value, &drops));
+        }
+    });
+
+    assert_eq!(lazy.get().value(), 4);
+    assert_eq!(drops.load(Ordering::SeqCst), 4);
+    drop(lazy);
+    assert_eq!(drops.load(Ordering::SeqCst), 5);
+}
+
+#[cfg(feature = "spin")]
+mod spin {
+    use super::*;
+    use rust_benchmarks::spin::SpinLazy;
+
+    #[test]
+    fn spin_lazy_concurrent_force() {
+        let drops = drop_counter();
+        let lazy = SpinLazy::new(|| Payload::new(42, &drops));
+
+        thread::scope(|s| {
+            for _ in 0..THREADS {
+                s.spawn(|| assert_eq!(lazy.value(), 42));
+            }
+        });
+        assert_eq!(SpinLazy::get(&lazy).unwrap().value(), 42);
+
+        drop(lazy);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn spin_lazy_uninit_drop() {
+        let drops = drop_counter();
+        let lazy: SpinLazy<Payload, _> = SpinLazy::new(|| Payload::new(42, &drops));
+        drop(lazy);
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn spin_lazy_poisoned() {
+        let lazy: SpinLazy<Payload, _> = SpinLazy::new(|| panic!("first attempt"));
+        assert!(catch_panic(|| lazy.value()).is_err());
+        assert!(catch_panic(|| lazy.value()).is_err());
+        assert!(SpinLazy::get(&lazy).is_none());
+    }
+}